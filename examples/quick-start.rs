@@ -1,4 +1,3 @@
-use serde_yaml;
 use service_policy_kit::data::{Context, SequenceInteractions};
 use service_policy_kit::runner::{RunOptions, SequenceRunner};
 use std::process::exit;
@@ -19,6 +18,6 @@ http_interactions:
     )
     .unwrap();
     let mut context = Context::new();
-    let res = runner.run(&mut context, &sequence.http_interactions);
+    let res = runner.run_sequence(&mut context, &sequence);
     exit(if res.ok { 0 } else { 1 })
 }
@@ -0,0 +1,413 @@
+use crate::checkpoint::Checkpoint;
+use crate::data::{
+    AsyncSender, Context, Interaction, Permit, ReporterConfig, Response, Runner, Sender,
+    SequenceInteractions,
+};
+use crate::events::RunEvents;
+use crate::registry::CheckRegistry;
+use crate::runner::{RunnerReport, SequenceRunner, Settings};
+use crate::selection::Selection;
+use anyhow::{anyhow, Result as AnyResult};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use tokio::sync::mpsc as async_mpsc;
+
+enum Relayed {
+    Send(Box<Interaction>, mpsc::Sender<AnyResult<Response>>),
+    Permit(String, mpsc::Sender<AnyResult<usize>>),
+    Release(usize),
+}
+
+// a blocking `Sender` for the runner on the blocking pool, handing each
+// request to the async runner's `AsyncSender` and waiting for its response
+struct RelaySender {
+    requests: async_mpsc::UnboundedSender<Relayed>,
+}
+
+impl RelaySender {
+    fn relay(&self, relayed: Relayed) -> AnyResult<()> {
+        self.requests
+            .send(relayed)
+            .map_err(|_| anyhow!("runner stopped relaying requests"))
+    }
+}
+
+// releases a permit held by the async task for the runner
+struct RelayPermit {
+    requests: async_mpsc::UnboundedSender<Relayed>,
+    id: usize,
+}
+
+impl Drop for RelayPermit {
+    fn drop(&mut self) {
+        let _ = self.requests.send(Relayed::Release(self.id));
+    }
+}
+
+impl Sender for RelaySender {
+    fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+        let (tx, rx) = mpsc::channel();
+        self.relay(Relayed::Send(Box::new(inter.clone()), tx))?;
+        rx.recv()?
+    }
+
+    fn permit(&self, uri: &str) -> AnyResult<Permit<'_>> {
+        let (tx, rx) = mpsc::channel();
+        self.relay(Relayed::Permit(uri.to_string(), tx))?;
+        let id = rx.recv()??;
+        Ok(Box::new(RelayPermit {
+            requests: self.requests.clone(),
+            id,
+        }))
+    }
+}
+
+/// `SequenceRunner` for async code, sending requests through an `AsyncSender`.
+///
+/// The run itself is a `SequenceRunner` on the blocking pool, so every check
+/// behaves as it does there. Only its requests come back to the async task,
+/// to be sent by the async sender.
+#[allow(clippy::module_name_repetitions)]
+pub struct AsyncSequenceRunner<'a> {
+    sender: &'a dyn AsyncSender,
+    registry: CheckRegistry,
+    settings: Settings,
+    checkpoint: Option<PathBuf>,
+}
+
+impl<'a> AsyncSequenceRunner<'a> {
+    pub fn new(
+        sender: &'a dyn AsyncSender,
+        flip: bool,
+        reporters: HashMap<String, ReporterConfig>,
+    ) -> Self {
+        Self {
+            sender,
+            registry: CheckRegistry::new(),
+            settings: Settings {
+                flip,
+                reporters,
+                runner: Runner::default(),
+                selection: Selection::default(),
+                events: None,
+            },
+            checkpoint: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_registry(mut self, registry: CheckRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    #[must_use]
+    pub fn with_runner(mut self, runner: Runner) -> Self {
+        self.settings.runner = runner;
+        self
+    }
+
+    #[must_use]
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.settings.selection = selection;
+        self
+    }
+
+    /// See `SequenceRunner::with_events`.
+    #[must_use]
+    pub fn with_events(mut self, events: RunEvents) -> Self {
+        self.settings.events = Some(events);
+        self
+    }
+
+    /// See `SequenceRunner::with_checkpoint`.
+    #[must_use]
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+
+    // hands a `SequenceRunner` like this one to `work` on the blocking pool,
+    // sending the requests it relays until it is done
+    async fn relay<T: Send + 'static>(
+        &self,
+        work: impl FnOnce(SequenceRunner<'_>) -> T + Send + 'static,
+    ) -> T {
+        let (requests, mut relayed) = async_mpsc::unbounded_channel::<Relayed>();
+        let registry = self.registry.clone();
+        let settings = self.settings.clone();
+        let checkpoint = self.checkpoint.clone();
+        let mut held = HashMap::new();
+        let mut next_id = 0;
+        let mut task = tokio::task::spawn_blocking(move || {
+            let sender = RelaySender { requests };
+            work(SequenceRunner::from_parts(
+                &sender, registry, settings, checkpoint,
+            ))
+        });
+        loop {
+            tokio::select! {
+                done = &mut task => {
+                    return done.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+                }
+                Some(relayed) = relayed.recv() => match relayed {
+                    Relayed::Send(inter, reply) => {
+                        let _ = reply.send(self.sender.send(&inter).await);
+                    }
+                    Relayed::Permit(uri, reply) => {
+                        let permit = self.sender.permit(&uri).await.map(|permit| {
+                            next_id += 1;
+                            held.insert(next_id, permit);
+                            next_id
+                        });
+                        let _ = reply.send(permit);
+                    }
+                    Relayed::Release(id) => {
+                        held.remove(&id);
+                    }
+                },
+            }
+        }
+    }
+
+    /// See `SequenceRunner::run`.
+    pub async fn run(&self, context: &mut Context, sequence: &[Interaction]) -> RunnerReport {
+        let mut ctx = context.clone();
+        let sequence = sequence.to_vec();
+        let (report, ctx) = self
+            .relay(move |runner| (runner.run(&mut ctx, &sequence), ctx))
+            .await;
+        *context = ctx;
+        report
+    }
+
+    /// See `SequenceRunner::run_sequence`.
+    pub async fn run_sequence(
+        &self,
+        context: &mut Context,
+        sequence: &SequenceInteractions,
+    ) -> RunnerReport {
+        let mut ctx = context.clone();
+        let sequence = sequence.clone();
+        let (report, ctx) = self
+            .relay(move |runner| (runner.run_sequence(&mut ctx, &sequence), ctx))
+            .await;
+        *context = ctx;
+        report
+    }
+
+    /// See `SequenceRunner::resume`.
+    pub async fn resume(
+        &self,
+        context: &mut Context,
+        sequence: &SequenceInteractions,
+        checkpoint: Checkpoint,
+        from: &str,
+    ) -> AnyResult<RunnerReport> {
+        let mut ctx = context.clone();
+        let sequence = sequence.clone();
+        let from = from.to_string();
+        let (report, ctx) = self
+            .relay(move |runner| {
+                let report = runner.resume(&mut ctx, &sequence, checkpoint, &from);
+                (report, ctx)
+            })
+            .await;
+        *context = ctx;
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Check, CheckResult};
+    use crate::sender::{AsyncReqwestSender, BlockingSender};
+    use mockito::{mock, server_address};
+
+    const ITC_SIMPLE: &str = include_str!("fixtures/simple.yaml");
+
+    fn context() -> Context {
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+        ctx
+    }
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[tokio::test]
+    async fn test_async_runner_passes_vars_along() {
+        let _m1 = mock("GET", "/one").with_body("next: two").create();
+        let _m2 = mock("GET", "/two").with_body("three").create();
+        let interactions = Interaction::sequence_interactions_from_yaml(ITC_SIMPLE).unwrap();
+        let sender = AsyncReqwestSender::new();
+        let runner = AsyncSequenceRunner::new(&sender, false, HashMap::new());
+        let mut ctx = context();
+
+        let run = runner.run(&mut ctx, &interactions);
+        assert_send(&run);
+        let report = run.await;
+
+        assert!(report.ok);
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(ctx.vars_bag.get("foo").unwrap(), "two");
+    }
+
+    #[tokio::test]
+    async fn test_async_runner_runs_builtin_and_registry_checks() {
+        let _m = mock("GET", "/headers")
+            .with_header("x-content-type-options", "nosniff")
+            .create();
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: headers
+    uri: http://{{host}}/headers
+  checks:
+    security_headers:
+      preset: baseline
+      hsts_min_max_age: null
+      frame_options: false
+    sends:
+"#,
+        )
+        .unwrap();
+        struct Sends<'a>(&'a dyn Sender);
+        impl Check for Sends<'_> {
+            fn name(&self) -> &str {
+                "sends"
+            }
+            fn perform(&self, context: &mut Context, interaction: &Interaction) -> CheckResult {
+                let mut res = CheckResult::invalid("sends", interaction);
+                res.response = interaction.send_with_context(self.0, context).ok();
+                res.error = None;
+                res
+            }
+        }
+        let mut registry = CheckRegistry::new();
+        registry.register("sends", |sender| Box::new(Sends(sender)));
+        let path =
+            std::env::temp_dir().join(format!("spk-async-checkpoint-{}.json", std::process::id()));
+        let sender = AsyncReqwestSender::new();
+        let runner = AsyncSequenceRunner::new(&sender, false, HashMap::new())
+            .with_registry(registry)
+            .with_checkpoint(&path);
+
+        let report = runner.run(&mut context(), &interactions).await;
+
+        let kinds = report
+            .results
+            .iter()
+            .map(|r| (r.kind.as_str(), r.error.clone(), r.violations.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![("security_headers", None, 0), ("sends", None, 0)]
+        );
+        assert_eq!(
+            report.results[1]
+                .response
+                .as_ref()
+                .unwrap()
+                .status_code
+                .as_deref(),
+            Some("200 OK")
+        );
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(checkpoint.last.as_deref(), Some("headers"));
+        assert_eq!(checkpoint.results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_async_runner_relays_permits_to_async_sender() {
+        use async_trait::async_trait;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        struct Held(Arc<AtomicUsize>);
+        impl Drop for Held {
+            fn drop(&mut self) {
+                self.0.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+        struct Counting(Arc<AtomicUsize>);
+        #[async_trait]
+        impl AsyncSender for Counting {
+            async fn send(&self, _interaction: &Interaction) -> AnyResult<Response> {
+                Err(anyhow!("not sending"))
+            }
+            async fn permit<'s>(&'s self, uri: &str) -> AnyResult<Permit<'s>> {
+                if uri.contains("refused") {
+                    return Err(anyhow!("refused"));
+                }
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(Held(self.0.clone())))
+            }
+        }
+        struct Connects<'a>(&'a dyn Sender, Arc<AtomicUsize>);
+        impl Check for Connects<'_> {
+            fn name(&self) -> &str {
+                "connects"
+            }
+            fn perform(&self, _context: &mut Context, interaction: &Interaction) -> CheckResult {
+                let mut res = CheckResult::invalid("connects", interaction);
+                res.error = match self.0.permit(&interaction.request.uri) {
+                    Ok(_permit) => Some(format!("held {}", self.1.load(Ordering::SeqCst))),
+                    Err(err) => Some(err.to_string()),
+                };
+                res
+            }
+        }
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: allowed
+    uri: https://allowed.example/
+  checks:
+    connects:
+- request:
+    id: refused
+    uri: https://refused.example/
+  checks:
+    connects:
+"#,
+        )
+        .unwrap();
+        let held = Arc::new(AtomicUsize::new(0));
+        let mut registry = CheckRegistry::new();
+        let counter = held.clone();
+        registry.register("connects", move |sender| {
+            Box::new(Connects(sender, counter.clone()))
+        });
+        let sender = Counting(held.clone());
+        let runner =
+            AsyncSequenceRunner::new(&sender, false, HashMap::new()).with_registry(registry);
+
+        let report = runner.run(&mut context(), &interactions).await;
+
+        let errors = report
+            .results
+            .iter()
+            .map(|r| r.error.clone().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(errors, vec!["held 1", "refused"]);
+        assert_eq!(held.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_blocking_sender_wraps_async_sender() {
+        let _m1 = mock("GET", "/one").with_body("next: two").create();
+        let _m2 = mock("GET", "/two").with_body("three").create();
+        let interactions = Interaction::sequence_interactions_from_yaml(ITC_SIMPLE).unwrap();
+        let sender = BlockingSender::new(AsyncReqwestSender::new()).unwrap();
+        let runner = SequenceRunner::new(&sender, false, HashMap::new());
+
+        let report = runner.run(&mut context(), &interactions);
+        assert!(report.ok);
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::Result as AnyResult;
+use serde::Deserialize;
+use std::time::Instant;
+
+use crate::data::{Cause, Check, CheckResult, Context, Interaction, Sender, Violation};
+use crate::registry::CheckConfig;
+pub const NAME: &str = "auth_enforcement";
+
+fn expect_status() -> Vec<u16> {
+    vec![401, 403]
+}
+
+fn credential_headers() -> Vec<String> {
+    vec!["authorization".to_string(), "cookie".to_string()]
+}
+
+/// What the `auth_enforcement` check expects when credentials are stripped
+/// from a request or tampered with:
+///
+/// ```yaml
+/// checks:
+///   auth_enforcement:
+///     expect_status: [401]
+///     credential_headers: [authorization, x-api-key]
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AuthPolicy {
+    #[serde(default = "expect_status")]
+    pub expect_status: Vec<u16>,
+    /// Headers carrying credentials, matched whatever their case.
+    #[serde(default = "credential_headers")]
+    pub credential_headers: Vec<String>,
+}
+
+impl CheckConfig for AuthPolicy {}
+
+impl Default for AuthPolicy {
+    fn default() -> Self {
+        Self {
+            expect_status: expect_status(),
+            credential_headers: credential_headers(),
+        }
+    }
+}
+
+impl AuthPolicy {
+    fn is_credential(&self, header: &str) -> bool {
+        self.credential_headers
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(header))
+    }
+
+    /// The stripped and tampered variants of `prepared`, by name. Only those
+    /// that change the request are returned.
+    #[must_use]
+    pub fn variants(&self, prepared: &Interaction) -> Vec<(&'static str, Interaction)> {
+        let req = &prepared.request;
+        let has_headers = req
+            .headers
+            .as_ref()
+            .is_some_and(|headers| headers.keys().any(|k| self.is_credential(k)));
+        let mut variants = vec![];
+        if req.basic_auth.is_some() {
+            let mut variant = prepared.clone();
+            variant.request.basic_auth = None;
+            variants.push(("no basic_auth", variant));
+        }
+        if req.aws_auth.is_some() {
+            let mut variant = prepared.clone();
+            variant.request.aws_auth = None;
+            variants.push(("no aws_auth", variant));
+        }
+        if has_headers {
+            let mut variant = prepared.clone();
+            self.strip_headers(&mut variant);
+            variants.push(("no credential headers", variant));
+        }
+        // with more than one kind of credential, each alone may still get in
+        let kinds = usize::from(req.basic_auth.is_some())
+            + usize::from(req.aws_auth.is_some())
+            + usize::from(has_headers);
+        if kinds > 1 {
+            variants.push(("no credentials", self.stripped(prepared)));
+        }
+        if let Some(variant) = self.tampered(prepared) {
+            variants.push(("tampered token", variant));
+        }
+        variants
+    }
+
+    /// `prepared` without any of its credentials.
+    #[must_use]
+    pub fn stripped(&self, prepared: &Interaction) -> Interaction {
+        let mut variant = prepared.clone();
+        variant.request.basic_auth = None;
+        variant.request.aws_auth = None;
+        self.strip_headers(&mut variant);
+        variant
+    }
+
+    /// `prepared` stripped of its credentials, with only its `Authorization`
+    /// token, or else its basic auth password (its user when it has no
+    /// password), put back tampered with. `None` when there is nothing to
+    /// tamper with.
+    fn tampered(&self, prepared: &Interaction) -> Option<Interaction> {
+        let mut variant = self.stripped(prepared);
+        let authorization = prepared.request.headers.as_ref().and_then(|headers| {
+            headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case("authorization"))
+        });
+        if let Some((name, values)) = authorization {
+            let tampered = values
+                .iter()
+                .filter_map(|value| match value.split_once(' ') {
+                    Some((scheme, token)) => {
+                        tamper(token.trim()).map(|token| format!("{} {}", scheme, token))
+                    }
+                    None => tamper(value),
+                })
+                .collect::<Vec<_>>();
+            if !tampered.is_empty() {
+                variant
+                    .request
+                    .headers
+                    .get_or_insert_with(Default::default)
+                    .insert(name.clone(), tampered);
+                return Some(variant);
+            }
+        }
+        let mut basic = prepared.request.basic_auth.clone()?;
+        match basic
+            .password
+            .clone()
+            .filter(|password| !password.is_empty())
+        {
+            Some(password) => basic.password = Some(tamper(&password)?),
+            None => basic.user = tamper(&basic.user)?,
+        }
+        variant.request.basic_auth = Some(basic);
+        Some(variant)
+    }
+
+    fn strip_headers(&self, variant: &mut Interaction) {
+        if let Some(headers) = variant.request.headers.as_mut() {
+            headers.retain(|k, _| !self.is_credential(k));
+        }
+    }
+}
+
+// a token with its first character changed, or of its last segment for a
+// JWT, so that the change always lands in signed bits; `None` when there is
+// no such character, as for an empty token or one ending in '.'
+fn tamper(token: &str) -> Option<String> {
+    let at = token.rfind('.').map_or(0, |dot| dot + 1);
+    let mut chars = token.chars().collect::<Vec<_>>();
+    let c = chars.get_mut(at)?;
+    *c = if *c == 'a' { 'b' } else { 'a' };
+    Some(chars.into_iter().collect())
+}
+
+/// Replays an interaction without its credentials, and with a tampered token,
+/// expecting each variant to be refused.
+pub struct AuthEnforcement<'a> {
+    sender: &'a dyn Sender,
+}
+
+impl<'a> AuthEnforcement<'a> {
+    pub fn new(sender: &'a dyn Sender) -> Self {
+        Self { sender }
+    }
+
+    fn replay(
+        &self,
+        policy: &AuthPolicy,
+        variants: Vec<(&'static str, Interaction)>,
+    ) -> AnyResult<Vec<Violation>> {
+        let mut violations = vec![];
+        for (name, variant) in variants {
+            let resp = self.sender.send(&variant)?;
+            let status = resp.status();
+            log::info!("{} {}: {:?}", NAME, name, resp.status_code);
+            if !status.is_some_and(|status| policy.expect_status.contains(&status)) {
+                violations.push(Violation {
+                    kind: NAME.to_string(),
+                    cause: Cause::Mismatch,
+                    subject: "status".to_string(),
+                    on: Some(name.to_string()),
+                    wire: resp.status_code,
+                    recorded: policy
+                        .expect_status
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" or "),
+                });
+            }
+        }
+        Ok(violations)
+    }
+}
+
+impl<'a> Check for AuthEnforcement<'a> {
+    fn name(&self) -> &str {
+        NAME
+    }
+    fn perform(&self, context: &mut Context, interaction: &Interaction) -> CheckResult {
+        let now = Instant::now();
+        let prepared = AuthPolicy::from_config(interaction.check_config(NAME)).and_then(|policy| {
+            let variants = policy.variants(&interaction.prepare_with(context)?);
+            Ok((policy, variants))
+        });
+        let found = match prepared {
+            Ok((_, variants)) if variants.is_empty() => {
+                return CheckResult::skipped(NAME, interaction, "no credentials to strip");
+            }
+            Ok((policy, variants)) => self.replay(&policy, variants).map(|v| (None, v)),
+            Err(err) => Err(err),
+        };
+        CheckResult::concluded(NAME, interaction, found, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::SequenceRunner;
+    use crate::sender::ReqwestSender;
+    use mockito::{mock, server_url, Matcher};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_auth_enforcement_replays_stripped_variants() {
+        let inter = Interaction::from_yaml(&format!(
+            r#"
+request:
+  id: private
+  uri: {}/private
+  headers:
+    Authorization:
+    - "Bearer {{{{token}}}}"
+    Cookie:
+    - session=s1
+checks:
+  auth_enforcement:
+    expect_status: [401]
+"#,
+            server_url()
+        ))
+        .unwrap();
+        assert_eq!(tamper("abc.def.ghi").as_deref(), Some("abc.def.ahi"));
+        assert_eq!(tamper("abc").as_deref(), Some("bbc"));
+        assert_eq!(tamper(""), None);
+        assert_eq!(tamper("abc.def."), None);
+        let mut context = Context::new();
+        context
+            .vars_bag
+            .insert("token".to_string(), "abc.def.ghi".to_string());
+        let sender = ReqwestSender::new();
+        let runner = SequenceRunner::new(&sender, false, HashMap::new());
+        let sequence = [inter];
+        let mut found = || {
+            runner.run(&mut context, &sequence).results[0]
+                .violations
+                .iter()
+                .map(|v| format!("{}: {}", v.on.clone().unwrap(), v.wire.clone().unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        // the session cookie gets in, but no variant carries it
+        let m1 = mock("GET", "/private")
+            .match_header("cookie", "session=s1")
+            .with_status(200)
+            .create();
+        let m2 = mock("GET", "/private").with_status(401).create();
+        assert!(found().is_empty());
+        drop((m1, m2));
+
+        // a server that does not verify the token's signature
+        let _m1 = mock("GET", "/private")
+            .match_header("authorization", "Bearer abc.def.ahi")
+            .match_header("cookie", Matcher::Missing)
+            .with_status(200)
+            .create();
+        let _m2 = mock("GET", "/private").with_status(401).create();
+        assert_eq!(found(), vec!["tampered token: 200 OK"]);
+    }
+
+    #[test]
+    fn test_auth_enforcement_tampers_user_without_password() {
+        let policy = AuthPolicy::from_config(None).unwrap();
+        let inter = Interaction::from_yaml(
+            r#"
+request:
+  uri: https://example.com/private
+  basic_auth:
+    user: admin
+  headers:
+    Authorization:
+    - "Bearer "
+"#,
+        )
+        .unwrap();
+
+        let variants = policy.variants(&inter);
+
+        let names = variants.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "no basic_auth",
+                "no credential headers",
+                "no credentials",
+                "tampered token"
+            ]
+        );
+        let tampered = &variants[3].1.request;
+        let basic = tampered.basic_auth.clone().unwrap();
+        assert_eq!(basic.user, "bdmin");
+        assert_eq!(basic.password, None);
+        assert!(tampered.headers.as_ref().unwrap().is_empty());
+
+        let mut inter = inter;
+        inter.request.basic_auth = None;
+        let names = policy
+            .variants(&inter)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["no credential headers"]);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::data::{AWSAuth, BasicAuth, HeaderList, Interaction, Permit, Request, Response, Sender};
+use anyhow::{anyhow, Context as _, Result as AnyResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The parts of a rendered request that decide what the service responds.
+#[derive(Serialize)]
+struct Rendered<'a> {
+    method: String,
+    uri: &'a str,
+    basic_auth: &'a Option<BasicAuth>,
+    aws_auth: &'a Option<AWSAuth>,
+    headers: &'a Option<HashMap<String, HeaderList>>,
+    form: &'a Option<HashMap<String, String>>,
+    body: &'a Option<String>,
+    // left out when unset, so cassettes recorded before it keep their keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    follow_redirects: Option<bool>,
+}
+
+// FNV-1a, which unlike the std hashers is stable across releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Recorded responses, keyed by request id and a hash of the rendered request.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Cassette {
+    pub responses: BTreeMap<String, Response>,
+}
+
+impl Cassette {
+    /// `<request id>:<hash>`, where the hash covers the method, uri, auth,
+    /// headers, form and body of an already rendered request.
+    pub fn key(request: &Request) -> AnyResult<String> {
+        let rendered = Rendered {
+            method: request.method.as_deref().unwrap_or("GET").to_uppercase(),
+            uri: &request.uri,
+            basic_auth: &request.basic_auth,
+            aws_auth: &request.aws_auth,
+            headers: &request.headers,
+            form: &request.form,
+            body: &request.body,
+            follow_redirects: request.follow_redirects,
+        };
+        // a json value keeps object keys sorted, so maps hash the same every time
+        let canonical = serde_json::to_value(&rendered)?.to_string();
+        Ok(format!(
+            "{}:{:016x}",
+            request.get_id(),
+            fnv1a(canonical.as_bytes())
+        ))
+    }
+
+    pub fn load(path: &Path) -> AnyResult<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("cannot read cassette '{}'", path.display()))?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    pub fn save(&self, path: &Path) -> AnyResult<()> {
+        fs::write(path, serde_yaml::to_string(self)?)
+            .with_context(|| format!("cannot write cassette '{}'", path.display()))
+    }
+}
+
+/// Sends through another `Sender`, typically a `ReqwestSender`, and records
+/// every response into a cassette file that a `ReplaySender` can serve.
+///
+/// Responses recorded earlier into the same file are kept, and the file is
+/// written after every request.
+#[allow(clippy::module_name_repetitions)]
+pub struct RecordingSender {
+    inner: Box<dyn Sender>,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl RecordingSender {
+    pub fn new(inner: Box<dyn Sender>, path: impl Into<PathBuf>) -> AnyResult<Self> {
+        let path = path.into();
+        let cassette = if path.exists() {
+            Cassette::load(&path)?
+        } else {
+            Cassette::default()
+        };
+        Ok(Self {
+            inner,
+            path,
+            cassette: Mutex::new(cassette),
+        })
+    }
+}
+
+impl Sender for RecordingSender {
+    fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+        let key = Cassette::key(&inter.request)?;
+        let resp = self.inner.send(inter)?;
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.responses.insert(key, resp.clone());
+        cassette.save(&self.path)?;
+        Ok(resp)
+    }
+
+    fn permit(&self, uri: &str) -> AnyResult<Permit<'_>> {
+        self.inner.permit(uri)
+    }
+}
+
+/// Serves responses from a cassette recorded by `RecordingSender`, failing on
+/// any request that was not recorded.
+#[allow(clippy::module_name_repetitions)]
+pub struct ReplaySender {
+    cassette: Cassette,
+}
+
+impl ReplaySender {
+    #[must_use]
+    pub const fn new(cassette: Cassette) -> Self {
+        Self { cassette }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> AnyResult<Self> {
+        Ok(Self::new(Cassette::load(path.as_ref())?))
+    }
+}
+
+impl Sender for ReplaySender {
+    fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+        let key = Cassette::key(&inter.request)?;
+        self.cassette
+            .responses
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| anyhow!("no recorded response for '{}' in cassette", key))
+    }
+
+    // a replay stays offline, so nothing may connect around it
+    fn permit(&self, uri: &str) -> AnyResult<Permit<'_>> {
+        Err(anyhow!(
+            "cannot connect to '{}' while replaying a cassette",
+            uri
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sender::ReqwestSender;
+    use mockito::{mock, server_url};
+
+    #[test]
+    fn test_replay_serves_recorded_responses_only() {
+        let _m = mock("POST", "/cassette").with_body("recorded").create();
+        let path = std::env::temp_dir().join(format!("spk-cassette-{}.yaml", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut inter = Interaction::from_yaml(&format!(
+            r#"
+request:
+  id: rec
+  uri: {}/cassette
+  method: post
+  body: one
+"#,
+            server_url()
+        ))
+        .unwrap();
+
+        let recorder = RecordingSender::new(Box::new(ReqwestSender::new()), &path).unwrap();
+        let recorded = recorder.send(&inter).unwrap();
+        assert_eq!(recorded.body.as_deref(), Some("recorded"));
+
+        let replay = ReplaySender::from_file(&path).unwrap();
+        assert_eq!(replay.send(&inter).unwrap(), recorded);
+
+        inter.request.body = Some("two".to_string());
+        let err = replay.send(&inter).unwrap_err().to_string();
+        assert!(err.starts_with("no recorded response for 'rec:"));
+        assert!(recorder.permit("https://example.com/").is_ok());
+        assert!(replay.permit("https://example.com/").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -34,20 +34,20 @@ impl Check for Cert {
     }
     fn perform(&self, _context: &mut Context, inter: &Interaction) -> CheckResult {
         let mut violations = vec![];
-        if inter.cert.is_some() {
+        if let Some(cert_detail) = &inter.cert {
             let now = Instant::now();
             let connector = TlsConnector::new().unwrap();
             let url = match reqwest::Url::parse(inter.request.uri.as_str()) {
                 Ok(url) => url,
                 Err(err) => {
-                    return CheckResult {
-                        kind: NAME.to_string(),
-                        request: inter.request.clone(),
-                        violations: error_violation(&err.to_string()),
-                        response: None,
-                        duration: Some(now.elapsed()),
-                        error: Some(err.to_string()),
-                    }
+                    return CheckResult::new(
+                        NAME,
+                        inter.request.clone(),
+                        None,
+                        error_violation(&err.to_string()),
+                        now.elapsed(),
+                    )
+                    .with_error(&err.to_string())
                 }
             };
 
@@ -58,28 +58,27 @@ impl Check for Cert {
                     {
                         Ok(stream) => stream,
                         Err(err) => {
-                            return CheckResult {
-                                kind: NAME.to_string(),
-                                request: inter.request.clone(),
-                                violations: error_violation(&err.to_string()),
-                                response: None,
-                                duration: Some(now.elapsed()),
-                                error: None,
-                            }
+                            return CheckResult::new(
+                                NAME,
+                                inter.request.clone(),
+                                None,
+                                error_violation(&err.to_string()),
+                                now.elapsed(),
+                            )
                         }
                     };
                     let cert = stream.peer_certificate().unwrap().unwrap();
                     cert.to_der().unwrap()
                 }
                 Err(err) => {
-                    return CheckResult {
-                        kind: NAME.to_string(),
-                        request: inter.request.clone(),
-                        violations: error_violation(&err.to_string()),
-                        response: None,
-                        duration: Some(now.elapsed()),
-                        error: Some(err.to_string()),
-                    }
+                    return CheckResult::new(
+                        NAME,
+                        inter.request.clone(),
+                        None,
+                        error_violation(&err.to_string()),
+                        now.elapsed(),
+                    )
+                    .with_error(&err.to_string())
                 }
             };
 
@@ -96,10 +95,7 @@ impl Check for Cert {
                     v.not_after.tm_min as u32,
                     v.not_after.tm_sec as u32,
                 );
-            if dt
-                < chrono::Utc::now()
-                    + chrono::Duration::days(inter.cert.as_ref().unwrap().max_days as i64)
-            {
+            if dt < chrono::Utc::now() + chrono::Duration::days(cert_detail.max_days as i64) {
                 violations.push(Violation {
                     kind: NAME.to_string(),
                     cause: Cause::Mismatch,
@@ -110,11 +106,11 @@ impl Check for Cert {
                         dt,
                         (dt - chrono::Utc::now()).num_days()
                     )),
-                    recorded: format!("> {} days", inter.cert.as_ref().unwrap().max_days),
+                    recorded: format!("> {} days", cert_detail.max_days),
                 })
             }
 
-            if let Some(issuer_expr) = inter.cert.as_ref().unwrap().issuer.as_ref() {
+            if let Some(issuer_expr) = cert_detail.issuer.as_ref() {
                 let issuer = format!("{}", c.tbs_certificate.issuer);
                 let match_re = Regex::new(issuer_expr).unwrap();
                 if !match_re.is_match(&issuer).unwrap() {
@@ -129,7 +125,7 @@ impl Check for Cert {
                 }
             }
 
-            if let Some(subject_expr) = inter.cert.as_ref().unwrap().subject.as_ref() {
+            if let Some(subject_expr) = cert_detail.subject.as_ref() {
                 let subject = format!("{}", c.tbs_certificate.subject);
                 let match_re = Regex::new(subject_expr).unwrap();
                 if !match_re.is_match(&subject).unwrap() {
@@ -144,14 +140,7 @@ impl Check for Cert {
                 }
             }
 
-            CheckResult {
-                kind: NAME.to_string(),
-                request: inter.request.clone(),
-                violations,
-                response: None,
-                duration: Some(now.elapsed()),
-                error: None,
-            }
+            CheckResult::new(NAME, inter.request.clone(), None, violations, now.elapsed())
         } else {
             CheckResult::invalid(NAME, inter)
         }
@@ -0,0 +1,96 @@
+use crate::data::{CheckResult, Context, Interaction, SequenceInteractions};
+use anyhow::{anyhow, Context as _, Result as AnyResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// an interaction's own id, or one of its matrix / uri_list iterations
+fn is_same(id: &str, result_id: &str) -> bool {
+    result_id == id || result_id.starts_with(&format!("{id}["))
+}
+
+/// State of a run saved after an interaction, to resume the run from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Checkpoint {
+    pub context: Context,
+    pub results: Vec<CheckResult>,
+    /// Id of the interaction that finished last.
+    pub last: Option<String>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> AnyResult<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("cannot read checkpoint '{}'", path.display()))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, path: &Path) -> AnyResult<()> {
+        fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("cannot write checkpoint '{}'", path.display()))
+    }
+
+    /// Results recorded before the interaction with the given id ran.
+    #[must_use]
+    pub fn results_before(&self, id: &str) -> Vec<CheckResult> {
+        self.results
+            .iter()
+            .take_while(|r| !is_same(id, &r.request.get_id()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Saves a `Checkpoint` of a run after every interaction, when it has a path.
+#[derive(Default)]
+pub(crate) struct Journal {
+    pub(crate) path: Option<PathBuf>,
+    // results of the current run, saved with every checkpoint
+    results: Mutex<Vec<CheckResult>>,
+}
+
+impl Journal {
+    /// Starts a run that follows the `prior` results of an earlier one.
+    pub(crate) fn start(&self, prior: &[CheckResult]) {
+        *self.results.lock().unwrap() = prior.to_vec();
+    }
+
+    pub(crate) fn record(&self, context: &Context, interaction: &Interaction, res: &[CheckResult]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let mut results = self.results.lock().unwrap();
+        results.extend(res.iter().cloned());
+        let checkpoint = Checkpoint {
+            context: context.clone(),
+            results: results.clone(),
+            last: Some(interaction.request.get_id()),
+        };
+        if let Err(err) = checkpoint.save(path) {
+            log::error!("{:?}", err);
+        }
+    }
+}
+
+/// What is left of a sequence from the interaction with the given id on. A
+/// run resumed in the main interactions skips setup, since its effects are in
+/// the restored context, and teardown always stays.
+pub fn remaining(sequence: &SequenceInteractions, id: &str) -> AnyResult<SequenceInteractions> {
+    let position =
+        |interactions: &[Interaction]| interactions.iter().position(|i| i.request.get_id() == id);
+    let mut rest = sequence.clone();
+    if let Some(pos) = position(&sequence.setup) {
+        rest.setup.drain(..pos);
+    } else if let Some(pos) = position(&sequence.http_interactions) {
+        rest.setup.clear();
+        rest.http_interactions.drain(..pos);
+    } else if let Some(pos) = position(&sequence.teardown) {
+        rest.setup.clear();
+        rest.http_interactions.clear();
+        rest.teardown.drain(..pos);
+    } else {
+        return Err(anyhow!("no interaction '{}' to resume from", id));
+    }
+    Ok(rest)
+}
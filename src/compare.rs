@@ -0,0 +1,232 @@
+use crate::data::{CheckStatus, Context, SequenceInteractions, VarsBag};
+use crate::runner::{RunnerReport, SequenceRunner};
+use anyhow::Result as AnyResult;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// A named set of vars to run a sequence with, such as dev, staging or prod.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Environment {
+    pub name: String,
+    #[serde(default)]
+    pub vars: VarsBag,
+}
+
+impl Environment {
+    /// Reads a list of environments:
+    ///
+    /// ```yaml
+    /// - name: staging
+    ///   vars:
+    ///     host: staging.example.com
+    /// ```
+    pub fn list_from_yaml(content: &str) -> AnyResult<Vec<Self>> {
+        Ok(serde_yaml::from_str(content)?)
+    }
+}
+
+/// One check of one interaction, and how it ended in each environment.
+#[derive(Serialize, Debug, Clone)]
+pub struct Row {
+    pub id: String,
+    pub kind: String,
+    /// In the order of `Comparison::environments`, `None` where it did not run.
+    pub statuses: Vec<Option<CheckStatus>>,
+}
+
+impl Row {
+    /// Whether the check did not end the same everywhere.
+    #[must_use]
+    pub fn drifts(&self) -> bool {
+        self.statuses.windows(2).any(|w| w[0] != w[1])
+    }
+}
+
+/// Side by side results of the same sequence run in several environments.
+#[derive(Serialize)]
+pub struct Comparison {
+    pub environments: Vec<String>,
+    pub rows: Vec<Row>,
+    #[serde(skip)]
+    pub reports: Vec<RunnerReport>,
+}
+
+impl Comparison {
+    /// Runs `sequence` once per environment, each on a copy of `context`
+    /// with the environment's vars over its own.
+    pub fn run(
+        runner: &SequenceRunner<'_>,
+        context: &Context,
+        sequence: &SequenceInteractions,
+        environments: &[Environment],
+    ) -> Self {
+        let reports = environments
+            .iter()
+            .map(|env| {
+                let mut ctx = context.clone();
+                ctx.vars_bag.extend(env.vars.clone());
+                runner.run_sequence(&mut ctx, sequence)
+            })
+            .collect();
+        Self::from_reports(
+            environments.iter().map(|env| env.name.clone()).collect(),
+            reports,
+        )
+    }
+
+    /// Lines up reports, given in the order of `environments`, by request id
+    /// and check kind.
+    #[must_use]
+    pub fn from_reports(environments: Vec<String>, reports: Vec<RunnerReport>) -> Self {
+        let mut rows: Vec<Row> = vec![];
+        for (col, report) in reports.iter().enumerate() {
+            for res in &report.results {
+                let id = res.request.get_id();
+                let pos = rows
+                    .iter()
+                    .position(|r| r.id == id && r.kind == res.kind)
+                    .unwrap_or_else(|| {
+                        rows.push(Row {
+                            id,
+                            kind: res.kind.clone(),
+                            statuses: vec![None; environments.len()],
+                        });
+                        rows.len() - 1
+                    });
+                rows[pos].statuses[col] = Some(res.status());
+            }
+        }
+        Self {
+            environments,
+            rows,
+            reports,
+        }
+    }
+
+    /// Whether the run passed in every environment.
+    #[must_use]
+    pub fn ok(&self) -> bool {
+        self.reports.iter().all(|r| r.ok)
+    }
+
+    /// Rows of checks that ended differently across environments.
+    pub fn drifted(&self) -> impl Iterator<Item = &Row> {
+        self.rows.iter().filter(|r| r.drifts())
+    }
+
+    /// A plain text matrix with a column per environment, marking drifting
+    /// rows with `!`.
+    #[must_use]
+    pub fn to_table(&self) -> String {
+        let label = |row: &Row| format!("{} [{}]", row.id, row.kind);
+        let cell = |status: Option<CheckStatus>| match status {
+            Some(CheckStatus::Passed) => "pass",
+            Some(CheckStatus::Failed) => "FAIL",
+            Some(CheckStatus::Errored) => "ERROR",
+            Some(CheckStatus::Skipped) => "skip",
+            None => "-",
+        };
+        let first = self
+            .rows
+            .iter()
+            .map(|r| label(r).len())
+            .chain(std::iter::once("interaction".len()))
+            .max()
+            .unwrap_or_default();
+        let widths = self
+            .environments
+            .iter()
+            .map(|env| env.len().max("ERROR".len()))
+            .collect::<Vec<_>>();
+
+        let mut lines = vec![];
+        let mut line = format!("  {:first$}", "interaction");
+        for (env, width) in self.environments.iter().zip(&widths) {
+            write!(line, "  {env:width$}").unwrap();
+        }
+        lines.push(line);
+        for row in &self.rows {
+            let mark = if row.drifts() { "!" } else { " " };
+            let mut line = format!("{mark} {:first$}", label(row));
+            for (status, width) in row.statuses.iter().zip(&widths) {
+                write!(line, "  {:width$}", cell(*status)).unwrap();
+            }
+            lines.push(line);
+        }
+        let mut out = String::new();
+        for line in lines {
+            writeln!(out, "{}", line.trim_end()).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sender::ReqwestSender;
+    use mockito::{mock, server_address};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_comparison_shows_drift_between_environments() {
+        let _m1 = mock("GET", "/staging/page")
+            .with_header("x-frame-options", "DENY")
+            .create();
+        let _m2 = mock("GET", "/prod/page").create();
+        let _m3 = mock("GET", "/staging/health").create();
+        let _m4 = mock("GET", "/prod/health").create();
+        let sequence: SequenceInteractions = serde_yaml::from_str(
+            r#"
+http_interactions:
+- request:
+    id: page
+    uri: http://{{host}}/{{env}}/page
+  response:
+    headers:
+      x-frame-options:
+      - DENY
+- request:
+    id: health
+    uri: http://{{host}}/{{env}}/health
+  response:
+    status_code: "200"
+"#,
+        )
+        .unwrap();
+        let environments = Environment::list_from_yaml(
+            r#"
+- name: staging
+  vars:
+    env: staging
+- name: prod
+  vars:
+    env: prod
+"#,
+        )
+        .unwrap();
+        let sender = ReqwestSender::new();
+        let runner = SequenceRunner::new(&sender, false, HashMap::new());
+        let mut context = Context::new();
+        context
+            .vars_bag
+            .insert("host".to_string(), server_address().to_string());
+
+        let comparison = Comparison::run(&runner, &context, &sequence, &environments);
+
+        assert!(!comparison.ok());
+        assert_eq!(
+            comparison
+                .drifted()
+                .map(|r| r.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["page"]
+        );
+        assert_eq!(
+            comparison.to_table(),
+            "  interaction       staging  prod\n\
+             ! page [content]    pass     FAIL\n  \
+             health [content]  pass     pass\n"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::data::{placeholders_in, render_with_vars, VarsBag};
+use anyhow::{anyhow, Result as AnyResult};
+use fancy_regex::Regex;
+
+// the leftmost of `seps` that is not inside single or double quotes
+fn find_outside_quotes<'s>(text: &str, seps: &[&'s str]) -> Option<(usize, &'s str)> {
+    let mut quote: Option<char> = None;
+    for (idx, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => {
+                if let Some(sep) = seps.iter().find(|sep| text[idx..].starts_with(**sep)) {
+                    return Some((idx, sep));
+                }
+            }
+        }
+    }
+    None
+}
+
+// splits on `sep`, ignoring separators that appear inside single or double quotes
+fn split_outside_quotes<'t>(text: &'t str, sep: &str) -> Vec<&'t str> {
+    let mut parts = vec![];
+    let mut rest = text;
+    while let Some((idx, sep)) = find_outside_quotes(rest, &[sep]) {
+        parts.push(&rest[..idx]);
+        rest = &rest[idx + sep.len()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+// a quoted literal or text with placeholders; placeholders of unset vars render empty
+fn operand(text: &str, vars: &VarsBag, fmtstring: &str) -> String {
+    let text = text.trim();
+    let unquoted = if text.len() >= 2
+        && ((text.starts_with('"') && text.ends_with('"'))
+            || (text.starts_with('\'') && text.ends_with('\'')))
+    {
+        &text[1..text.len() - 1]
+    } else {
+        text
+    };
+    let rendered = render_with_vars(unquoted.to_string(), vars, fmtstring);
+    placeholders_in(&rendered, fmtstring)
+        .iter()
+        .fold(rendered.clone(), |acc, name| {
+            acc.replace(&fmtstring.replace("var", name), "")
+        })
+}
+
+// `{{name}}` or a bare `name`
+fn var_name(text: &str, fmtstring: &str) -> String {
+    let text = text.trim();
+    placeholders_in(text, fmtstring)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| text.to_string())
+}
+
+fn evaluate_term(term: &str, vars: &VarsBag, fmtstring: &str) -> AnyResult<bool> {
+    let term = term.trim();
+    if term.is_empty() {
+        return Err(anyhow!("empty condition"));
+    }
+    if let Some(name) = term.strip_suffix(" is not set") {
+        return Ok(!vars.contains_key(&var_name(name, fmtstring)));
+    }
+    if let Some(name) = term.strip_suffix(" is set") {
+        return Ok(vars.contains_key(&var_name(name, fmtstring)));
+    }
+    if let Some((idx, op)) = find_outside_quotes(term, &["==", "!=", "=~", "!~"]) {
+        let lhs = operand(&term[..idx], vars, fmtstring);
+        let rhs = operand(&term[idx + op.len()..], vars, fmtstring);
+        return match op {
+            "==" => Ok(lhs == rhs),
+            "!=" => Ok(lhs != rhs),
+            _ => {
+                let re = Regex::new(&rhs)?;
+                let found = re.is_match(&lhs)?;
+                Ok(if op == "=~" { found } else { !found })
+            }
+        };
+    }
+    let value = operand(term, vars, fmtstring);
+    Ok(!(value.is_empty() || value == "false" || value == "0"))
+}
+
+/// Evaluates a `when:` expression against the vars bag.
+///
+/// Terms compare operands with `==`, `!=`, `=~` or `!~` (regex), test a var
+/// with `{{name}} is set` / `is not set`, or take a single operand as truthy
+/// when it is non-empty and not `false` or `0`. Terms combine with `&&` and
+/// `||`, where `&&` binds tighter.
+pub fn evaluate(expr: &str, vars: &VarsBag, fmtstring: &str) -> AnyResult<bool> {
+    for alternative in split_outside_quotes(expr, "||") {
+        let mut all = true;
+        for term in split_outside_quotes(alternative, "&&") {
+            all &= evaluate_term(term, vars, fmtstring)?;
+        }
+        if all {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_evaluate() {
+        let vars = hashmap! {
+            "env".to_string() => "prod".to_string(),
+            "flag".to_string() => "false".to_string(),
+        };
+        let fmt = "{{var}}";
+        assert!(evaluate(r#"{{env}} == "prod""#, &vars, fmt).unwrap());
+        assert!(!evaluate("{{env}} != 'prod'", &vars, fmt).unwrap());
+        assert!(evaluate("{{env}} =~ ^pr", &vars, fmt).unwrap());
+        assert!(evaluate("{{env}} is set", &vars, fmt).unwrap());
+        assert!(evaluate("tenant is not set", &vars, fmt).unwrap());
+        assert!(!evaluate("{{flag}}", &vars, fmt).unwrap());
+        assert!(!evaluate("{{tenant}}", &vars, fmt).unwrap());
+        assert!(evaluate(
+            r#"{{env}} == "dev" || {{env}} == "prod" && {{tenant}} == """#,
+            &vars,
+            fmt
+        )
+        .unwrap());
+        assert!(!evaluate(r#"{{env}} == "a||b""#, &vars, fmt).unwrap());
+        assert!(evaluate("{{env}} =~ (", &vars, fmt).is_err());
+        // the leftmost operator splits, not one inside the regex
+        assert!(!evaluate("{{env}} =~ a!=b", &vars, fmt).unwrap());
+        assert!(evaluate("{{env}} !~ ^(a|b)==", &vars, fmt).unwrap());
+    }
+}
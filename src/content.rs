@@ -1,6 +1,7 @@
+use anyhow::Result as AnyResult;
 use std::time::Instant;
 
-use crate::data::{Check, CheckResult, Context, Interaction, Sender};
+use crate::data::{Check, CheckResult, Context, Interaction, Response, Sender};
 use crate::matcher::RegexMatcher;
 pub const NAME: &str = "content";
 
@@ -14,6 +15,37 @@ impl<'a> ContentCheck<'a> {
     }
 }
 
+// main func should always return check result
+// match here and move err into CheckResult.err
+fn evaluate(interaction: &Interaction, r: AnyResult<Response>, now: Instant) -> CheckResult {
+    match r {
+        Ok(resp) => {
+            let matcher = RegexMatcher::new(NAME);
+            let vs = matcher.is_match(&resp, interaction.invalid.as_ref());
+            if vs.is_empty() {
+                return CheckResult::invalid_err(NAME, interaction, "matched invalid response");
+            }
+            let vs = matcher.is_match(&resp, interaction.response.as_ref());
+
+            CheckResult::new(
+                NAME,
+                interaction.request.clone(),
+                Some(resp),
+                vs,
+                now.elapsed(),
+            )
+        }
+        Err(err) => CheckResult::new(
+            NAME,
+            interaction.request.clone(),
+            None,
+            vec![],
+            now.elapsed(),
+        )
+        .with_error(&err.to_string()),
+    }
+}
+
 impl<'a> Check for ContentCheck<'a> {
     fn name(&self) -> &str {
         NAME
@@ -21,40 +53,8 @@ impl<'a> Check for ContentCheck<'a> {
     fn perform(&self, context: &mut Context, interaction: &Interaction) -> CheckResult {
         if interaction.response.is_some() {
             let now = Instant::now();
-            // main func should always return check result
-            // match here and move err into CheckResult.err
             let r = interaction.send_with_context(self.sender, context);
-            match r {
-                Ok(resp) => {
-                    let matcher = RegexMatcher::new(NAME);
-                    let vs = matcher.is_match(&resp, interaction.invalid.as_ref());
-                    if vs.is_empty() {
-                        return CheckResult::invalid_err(
-                            self.name(),
-                            interaction,
-                            "matched invalid response",
-                        );
-                    }
-                    let vs = matcher.is_match(&resp, interaction.response.as_ref());
-
-                    CheckResult {
-                        kind: NAME.to_string(),
-                        request: interaction.request.clone(),
-                        response: Some(resp),
-                        violations: vs,
-                        duration: Some(now.elapsed()),
-                        error: None,
-                    }
-                }
-                Err(err) => CheckResult {
-                    kind: NAME.to_string(),
-                    request: interaction.request.clone(),
-                    response: None,
-                    violations: vec![],
-                    duration: Some(now.elapsed()),
-                    error: Some(err.to_string()),
-                },
-            }
+            evaluate(interaction, r, now)
         } else {
             CheckResult::invalid(self.name(), interaction)
         }
@@ -0,0 +1,430 @@
+use anyhow::{anyhow, Result as AnyResult};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+use crate::data::{Cause, Check, CheckResult, Context, Interaction, Sender, Violation};
+use crate::registry::CheckConfig;
+use crate::selection::glob_match;
+pub const NAME: &str = "cookies";
+
+/// What cookies with a matching name must look like. Unset fields are not
+/// checked.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct CookieRule {
+    pub secure: Option<bool>,
+    pub http_only: Option<bool>,
+    /// Accepted `SameSite` values, such as `[strict, lax]`.
+    pub same_site: Option<Vec<String>>,
+    /// Longest lifetime in seconds, from `Max-Age` or `Expires`.
+    pub max_age: Option<i64>,
+    /// Widest `Domain` the cookie may be scoped to.
+    pub domain: Option<String>,
+    /// No `Domain` at all, so the cookie goes back to its own host only.
+    pub host_only: Option<bool>,
+}
+
+fn enforce_prefixes() -> bool {
+    true
+}
+
+/// Rules for the `cookies` check, keyed by cookie name globs:
+///
+/// ```yaml
+/// checks:
+///   cookies:
+///     rules:
+///       "session*":
+///         secure: true
+///         http_only: true
+///         same_site: [strict, lax]
+///         max_age: 86400
+/// ```
+///
+/// The `__Host-` and `__Secure-` prefix rules apply to every cookie unless
+/// `prefixes` is false.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CookiePolicy {
+    #[serde(default)]
+    pub rules: BTreeMap<String, CookieRule>,
+    #[serde(default = "enforce_prefixes")]
+    pub prefixes: bool,
+}
+
+impl CheckConfig for CookiePolicy {}
+
+impl CookiePolicy {
+    /// Every way the cookies set in `headers` break the policy.
+    #[must_use]
+    pub fn evaluate(
+        &self,
+        headers: &HashMap<String, Vec<String>>,
+        now: DateTime<Utc>,
+    ) -> Vec<Violation> {
+        let mut violations = vec![];
+        for cookie in set_cookies(headers) {
+            if self.prefixes {
+                violations.extend(cookie.prefix_violations());
+            }
+            for (pattern, rule) in &self.rules {
+                if glob_match(pattern, &cookie.name) {
+                    violations.extend(cookie.violations(rule, now));
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// One `Set-Cookie` header, with attribute names lowercased.
+#[derive(Debug, Clone)]
+pub struct SetCookie {
+    pub name: String,
+    pub raw: String,
+    pub attributes: Vec<(String, Option<String>)>,
+}
+
+impl SetCookie {
+    #[must_use]
+    pub fn parse(raw: &str) -> Self {
+        let mut parts = raw.split(';');
+        let name = parts
+            .next()
+            .and_then(|pair| pair.split('=').next())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let attributes = parts
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(|part| match part.split_once('=') {
+                Some((key, value)) => (key.trim().to_lowercase(), Some(value.trim().to_string())),
+                None => (part.to_lowercase(), None),
+            })
+            .collect();
+        Self {
+            name,
+            raw: raw.to_string(),
+            attributes,
+        }
+    }
+
+    #[must_use]
+    pub fn has(&self, name: &str) -> bool {
+        self.attributes.iter().any(|(key, _)| key == name)
+    }
+
+    /// The value of the last attribute with this name, as browsers use it.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Seconds the cookie lives for, `None` for a session cookie. An error
+    /// when `Max-Age` is not a number or `Expires` not a cookie-date.
+    pub fn lifetime(&self, now: DateTime<Utc>) -> AnyResult<Option<i64>> {
+        if let Some(age) = self.get("max-age") {
+            return Ok(Some(
+                age.parse()
+                    .map_err(|_| anyhow!("Max-Age '{}' is not a number", age))?,
+            ));
+        }
+        self.get("expires")
+            .map(|at| {
+                cookie_date(at)
+                    .map(|at| (at - now).num_seconds())
+                    .ok_or_else(|| anyhow!("Expires '{}' is not a date", at))
+            })
+            .transpose()
+    }
+
+    fn violation(&self, cause: Cause, recorded: &str) -> Violation {
+        Violation {
+            kind: NAME.to_string(),
+            cause,
+            subject: "set-cookie".to_string(),
+            on: Some(self.name.clone()),
+            wire: Some(self.raw.clone()),
+            recorded: recorded.to_string(),
+        }
+    }
+
+    fn flag_violation(&self, flag: &str, label: &str, wanted: Option<bool>) -> Option<Violation> {
+        match wanted {
+            Some(true) if !self.has(flag) => Some(self.violation(Cause::WireMissing, label)),
+            Some(false) if self.has(flag) => {
+                Some(self.violation(Cause::Mismatch, &format!("no {label}")))
+            }
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn prefix_violations(&self) -> Vec<Violation> {
+        let mut violations = vec![];
+        if self.name.starts_with("__Secure-") || self.name.starts_with("__Host-") {
+            violations.extend(self.flag_violation("secure", "Secure", Some(true)));
+        }
+        if self.name.starts_with("__Host-") {
+            if self.has("domain") {
+                violations.push(self.violation(Cause::Mismatch, "no Domain for __Host-"));
+            }
+            if self.get("path") != Some("/") {
+                violations.push(self.violation(Cause::Mismatch, "Path=/ for __Host-"));
+            }
+        }
+        violations
+    }
+
+    #[must_use]
+    pub fn violations(&self, rule: &CookieRule, now: DateTime<Utc>) -> Vec<Violation> {
+        let mut violations = vec![];
+        violations.extend(self.flag_violation("secure", "Secure", rule.secure));
+        violations.extend(self.flag_violation("httponly", "HttpOnly", rule.http_only));
+        if let Some(accepted) = &rule.same_site {
+            let same_site = self.get("samesite").unwrap_or_default();
+            if !accepted.iter().any(|a| a.eq_ignore_ascii_case(same_site)) {
+                violations.push(self.violation(
+                    Cause::Mismatch,
+                    &format!("SameSite in {}", accepted.join(", ")),
+                ));
+            }
+        }
+        if let Some(max_age) = rule.max_age {
+            match self.lifetime(now) {
+                Ok(Some(lifetime)) if lifetime > max_age => {
+                    violations
+                        .push(self.violation(Cause::Mismatch, &format!("Max-Age <= {max_age}")));
+                }
+                Ok(_) => {}
+                Err(err) => violations
+                    .push(self.violation(Cause::Mismatch, &format!("Max-Age <= {max_age}, {err}"))),
+            }
+        }
+        let domain = self
+            .get("domain")
+            .map(|d| d.trim_start_matches('.').to_lowercase());
+        if rule.host_only == Some(true) && domain.is_some() {
+            violations.push(self.violation(Cause::Mismatch, "no Domain"));
+        }
+        if let (Some(allowed), Some(domain)) = (&rule.domain, &domain) {
+            let allowed = allowed.trim_start_matches('.').to_lowercase();
+            if *domain != allowed && !domain.ends_with(&format!(".{allowed}")) {
+                violations
+                    .push(self.violation(Cause::Mismatch, &format!("Domain within {allowed}")));
+            }
+        }
+        violations
+    }
+}
+
+// the 1 to `max` digits `token` starts with, and what follows them
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<(u32, &str)> {
+    let len = token
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(token.len());
+    if len < min || len > max {
+        return None;
+    }
+    Some((token[..len].parse().ok()?, &token[len..]))
+}
+
+// `hh:mm:ss`, each part one or two digits
+fn hms(token: &str) -> Option<(u32, u32, u32)> {
+    let (hour, rest) = leading_digits(token, 1, 2)?;
+    let (minute, rest) = leading_digits(rest.strip_prefix(':')?, 1, 2)?;
+    let (second, _) = leading_digits(rest.strip_prefix(':')?, 1, 2)?;
+    Some((hour, minute, second))
+}
+
+/// An `Expires` date, read the way RFC 6265 has browsers read cookie-dates,
+/// so `Wed, 21 Oct 2015 07:28:00 GMT`, `Wed, 21-Oct-2015 07:28:00 GMT`,
+/// `Wednesday, 21-Oct-15 07:28:00 GMT` and `Wed Oct 21 07:28:00 2015` all
+/// parse.
+#[must_use]
+pub fn cookie_date(text: &str) -> Option<DateTime<Utc>> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let is_delimiter = |c: char| matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~');
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in text.split(is_delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() {
+            if let Some(found) = hms(token) {
+                time = Some(found);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some((found, _)) = leading_digits(token, 1, 2) {
+                day = Some(found);
+                continue;
+            }
+        }
+        if month.is_none() {
+            let prefix = token.get(..3).unwrap_or_default().to_lowercase();
+            if let Some(found) = MONTHS.iter().position(|m| *m == prefix) {
+                month = Some(found as u32 + 1);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some((found, _)) = leading_digits(token, 2, 4) {
+                year = Some(match found {
+                    70..=99 => found + 1900,
+                    0..=69 => found + 2000,
+                    _ => found,
+                });
+            }
+        }
+    }
+    let (hour, minute, second) = time?;
+    let year = year.filter(|year| *year >= 1601)?;
+    let date = NaiveDate::from_ymd_opt(year as i32, month?, day?)?;
+    Some(DateTime::from_utc(
+        date.and_hms_opt(hour, minute, second)?,
+        Utc,
+    ))
+}
+
+/// Every `Set-Cookie` header of a response, in the order received.
+#[must_use]
+pub fn set_cookies(headers: &HashMap<String, Vec<String>>) -> Vec<SetCookie> {
+    headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("set-cookie"))
+        .flat_map(|(_, values)| values.iter().map(|raw| SetCookie::parse(raw)))
+        .collect()
+}
+
+/// Sends the interaction's request and holds every cookie it sets against a
+/// `CookiePolicy`.
+pub struct Cookies<'a> {
+    sender: &'a dyn Sender,
+}
+
+impl<'a> Cookies<'a> {
+    pub fn new(sender: &'a dyn Sender) -> Self {
+        Self { sender }
+    }
+}
+
+impl<'a> Check for Cookies<'a> {
+    fn name(&self) -> &str {
+        NAME
+    }
+    fn perform(&self, context: &mut Context, interaction: &Interaction) -> CheckResult {
+        let now = Instant::now();
+        let found = CookiePolicy::from_config(interaction.check_config(NAME)).and_then(|policy| {
+            let resp = interaction.send_with_context(self.sender, context)?;
+            let violations =
+                policy.evaluate(resp.headers.as_ref().unwrap_or(&HashMap::new()), Utc::now());
+            Ok((Some(resp), violations))
+        });
+        CheckResult::concluded(NAME, interaction, found, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::SequenceRunner;
+    use crate::sender::ReqwestSender;
+    use mockito::{mock, server_url};
+
+    #[test]
+    fn test_cookies_checks_each_cookie_by_name() {
+        let _m = mock("GET", "/cookies")
+            .with_header(
+                "set-cookie",
+                "session_id=abc; Secure; HttpOnly; SameSite=None; Max-Age=604800",
+            )
+            .with_header(
+                "set-cookie",
+                "session_csrf=def; Secure; HttpOnly; SameSite=Lax",
+            )
+            .with_header("set-cookie", "__Host-pref=dark; Secure; Path=/app")
+            .with_header("set-cookie", "theme=dark; Domain=.example.com")
+            .create();
+        let inter = Interaction::from_yaml(&format!(
+            r#"
+request:
+  id: login
+  uri: {}/cookies
+checks:
+  cookies:
+    rules:
+      "session*":
+        secure: true
+        http_only: true
+        same_site: [strict, lax]
+        max_age: 86400
+      theme:
+        domain: app.example.com
+"#,
+            server_url()
+        ))
+        .unwrap();
+        let sender = ReqwestSender::new();
+        let runner = SequenceRunner::new(&sender, false, HashMap::new());
+        let report = runner.run(&mut Context::new(), &[inter]);
+
+        let found = report.results[0]
+            .violations
+            .iter()
+            .map(|v| format!("{}: {}", v.on.clone().unwrap(), v.recorded))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                "session_id: SameSite in strict, lax",
+                "session_id: Max-Age <= 86400",
+                "__Host-pref: Path=/ for __Host-",
+                "theme: Domain within app.example.com",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cookies_read_cookie_dates() {
+        let at = cookie_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(at.to_rfc3339(), "2015-10-21T07:28:00+00:00");
+        for text in [
+            "Wed, 21-Oct-2015 07:28:00 GMT",
+            "Wednesday, 21-Oct-15 07:28:00 GMT",
+            "Wed Oct 21 07:28:00 2015",
+        ] {
+            assert_eq!(cookie_date(text), Some(at), "{text}");
+        }
+        assert_eq!(cookie_date("Wed, 32-Oct-2015 07:28:00 GMT"), None);
+
+        let rule = CookieRule {
+            max_age: Some(86400),
+            ..CookieRule::default()
+        };
+        let now = cookie_date("Wed, 14-Oct-2015 07:28:00 GMT").unwrap();
+        let found = [
+            "session=a; Expires=Wed, 21-Oct-2015 07:28:00 GMT",
+            "session=b; Expires=Thu, 15-Oct-2015 07:28:00 GMT",
+            "session=c; Expires=next week",
+        ]
+        .iter()
+        .flat_map(|raw| SetCookie::parse(raw).violations(&rule, now))
+        .map(|v| v.recorded)
+        .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                "Max-Age <= 86400",
+                "Max-Age <= 86400, Expires 'next week' is not a date",
+            ]
+        );
+    }
+}
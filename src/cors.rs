@@ -0,0 +1,285 @@
+use anyhow::{anyhow, Result as AnyResult};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::data::{Cause, Check, CheckResult, Context, Interaction, Response, Sender, Violation};
+use crate::registry::CheckConfig;
+use crate::security_headers::header;
+pub const NAME: &str = "cors";
+
+const ATTACKER_ORIGIN: &str = "https://attacker.example";
+
+/// How the `cors` check probes an endpoint, given under `checks:`:
+///
+/// ```yaml
+/// checks:
+///   cors:
+///     trusted_origin: https://app.example.com
+///     allowed_methods: [GET, POST]
+///     allowed_headers: [content-type, authorization]
+/// ```
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsPolicy {
+    /// The origin that may make credentialed requests, the request's own
+    /// origin unless given.
+    pub trusted_origin: Option<String>,
+    pub attacker_origin: Option<String>,
+    /// Methods a preflight may allow, any but `*` when not given.
+    pub allowed_methods: Option<Vec<String>>,
+    /// Request headers a preflight may allow, any but `*` when not given.
+    pub allowed_headers: Option<Vec<String>>,
+}
+
+impl CheckConfig for CorsPolicy {}
+
+impl CorsPolicy {
+    /// The trusted, attacker and `null` origins, in the order they are probed.
+    pub fn origins(&self, uri: &str) -> AnyResult<Vec<String>> {
+        let trusted = match &self.trusted_origin {
+            Some(origin) => origin.clone(),
+            None => reqwest::Url::parse(uri)
+                .map_err(|err| anyhow!("cannot tell the origin of '{}': {}", uri, err))?
+                .origin()
+                .ascii_serialization(),
+        };
+        Ok(vec![
+            trusted,
+            self.attacker_origin
+                .clone()
+                .unwrap_or_else(|| ATTACKER_ORIGIN.to_string()),
+            "null".to_string(),
+        ])
+    }
+
+    /// Findings in the response to one probe, sent from `origin`.
+    #[must_use]
+    pub fn evaluate(
+        &self,
+        probe: &str,
+        origin: &str,
+        trusted: bool,
+        headers: &HashMap<String, Vec<String>>,
+    ) -> Vec<Violation> {
+        let on = format!("{probe} from {origin}");
+        let mut violations = vec![];
+        let credentials = header(headers, "access-control-allow-credentials")
+            .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"));
+
+        if let Some(allowed) = header(headers, "access-control-allow-origin") {
+            let allowed = allowed.trim();
+            if allowed == "*" && credentials {
+                violations.push(finding(
+                    "access-control-allow-origin",
+                    &on,
+                    allowed,
+                    "no wildcard origin with credentials",
+                ));
+            } else if !trusted && allowed == origin && credentials {
+                violations.push(finding(
+                    "access-control-allow-origin",
+                    &on,
+                    allowed,
+                    "untrusted origin not allowed with credentials",
+                ));
+            }
+            let varies = header(headers, "vary").is_some_and(|vary| {
+                vary.split(',')
+                    .any(|v| v.trim() == "*" || v.trim().eq_ignore_ascii_case("origin"))
+            });
+            if allowed != "*" && !varies {
+                violations.push(Violation {
+                    kind: NAME.to_string(),
+                    cause: Cause::WireMissing,
+                    subject: "vary".to_string(),
+                    on: Some(on.clone()),
+                    wire: None,
+                    recorded: "Origin".to_string(),
+                });
+            }
+        }
+
+        if probe == "preflight" {
+            violations.extend(broad(
+                "access-control-allow-methods",
+                &on,
+                headers,
+                self.allowed_methods.as_deref(),
+            ));
+            violations.extend(broad(
+                "access-control-allow-headers",
+                &on,
+                headers,
+                self.allowed_headers.as_deref(),
+            ));
+        }
+        violations
+    }
+}
+
+// a wildcard, or entries of a list header beyond what is allowed
+fn broad(
+    name: &str,
+    on: &str,
+    headers: &HashMap<String, Vec<String>>,
+    allowed: Option<&[String]>,
+) -> Option<Violation> {
+    let value = header(headers, name)?;
+    let entries = value.split(',').map(str::trim).filter(|e| !e.is_empty());
+    let extra = entries
+        .filter(|e| {
+            *e == "*"
+                || allowed.is_some_and(|allowed| !allowed.iter().any(|a| a.eq_ignore_ascii_case(e)))
+        })
+        .collect::<Vec<_>>();
+    (!extra.is_empty()).then(|| {
+        finding(
+            name,
+            on,
+            &value,
+            &allowed.map_or_else(|| "no *".to_string(), |allowed| allowed.join(", ")),
+        )
+    })
+}
+
+fn finding(name: &str, on: &str, wire: &str, recorded: &str) -> Violation {
+    Violation {
+        kind: NAME.to_string(),
+        cause: Cause::Mismatch,
+        subject: name.to_string(),
+        on: Some(on.to_string()),
+        wire: Some(wire.to_string()),
+        recorded: recorded.to_string(),
+    }
+}
+
+/// Sends a preflight `OPTIONS` and the request itself from a trusted, an
+/// attacker and the `null` origin, and reports every risky CORS response.
+pub struct Cors<'a> {
+    sender: &'a dyn Sender,
+}
+
+impl<'a> Cors<'a> {
+    pub fn new(sender: &'a dyn Sender) -> Self {
+        Self { sender }
+    }
+
+    fn probe(&self, prepared: &Interaction, preflight: bool, origin: &str) -> AnyResult<Response> {
+        let mut probe = prepared.clone();
+        let method = prepared
+            .request
+            .method
+            .as_deref()
+            .unwrap_or("GET")
+            .to_uppercase();
+        let headers = probe.request.headers.get_or_insert_with(HashMap::new);
+        if preflight {
+            let mut requested = headers.keys().cloned().collect::<Vec<_>>();
+            requested.sort();
+            let requested = requested.join(", ");
+            headers.insert("Access-Control-Request-Method".to_string(), vec![method]);
+            if !requested.is_empty() {
+                headers.insert(
+                    "Access-Control-Request-Headers".to_string(),
+                    vec![requested],
+                );
+            }
+            probe.request.method = Some("OPTIONS".to_string());
+            probe.request.body = None;
+            probe.request.form = None;
+        }
+        headers.insert("Origin".to_string(), vec![origin.to_string()]);
+        self.sender.send(&probe)
+    }
+}
+
+impl<'a> Check for Cors<'a> {
+    fn name(&self) -> &str {
+        NAME
+    }
+    fn perform(&self, context: &mut Context, interaction: &Interaction) -> CheckResult {
+        let now = Instant::now();
+        let found = CorsPolicy::from_config(interaction.check_config(NAME)).and_then(|policy| {
+            let prepared = interaction.prepare_with(context)?;
+            let origins = policy.origins(&prepared.request.uri)?;
+            let mut violations = vec![];
+            for (i, origin) in origins.iter().enumerate() {
+                for (probe, preflight) in [("preflight", true), ("request", false)] {
+                    let resp = self.probe(&prepared, preflight, origin)?;
+                    violations.extend(policy.evaluate(
+                        probe,
+                        origin,
+                        i == 0,
+                        resp.headers.as_ref().unwrap_or(&HashMap::new()),
+                    ));
+                }
+            }
+            Ok((None, violations))
+        });
+        CheckResult::concluded(NAME, interaction, found, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::SequenceRunner;
+    use crate::sender::ReqwestSender;
+    use mockito::{mock, server_url, Matcher};
+
+    #[test]
+    fn test_cors_flags_each_risky_response() {
+        let _m1 = mock("OPTIONS", "/cors")
+            .match_header("origin", Matcher::Any)
+            .with_header("access-control-allow-origin", "null")
+            .with_header("access-control-allow-credentials", "true")
+            .with_header("access-control-allow-methods", "GET, DELETE")
+            .create();
+        let _m2 = mock("GET", "/cors")
+            .match_header("origin", "https://attacker.example")
+            .with_header("access-control-allow-origin", "*")
+            .with_header("access-control-allow-credentials", "true")
+            .create();
+        let _m3 = mock("GET", "/cors")
+            .match_header("origin", Matcher::Any)
+            .with_header("vary", "Origin")
+            .create();
+        let inter = Interaction::from_yaml(&format!(
+            r#"
+request:
+  id: cors
+  uri: {}/cors
+checks:
+  cors:
+    allowed_methods: [GET]
+"#,
+            server_url()
+        ))
+        .unwrap();
+        let sender = ReqwestSender::new();
+        let runner = SequenceRunner::new(&sender, false, HashMap::new());
+        let report = runner.run(&mut Context::new(), &[inter]);
+
+        let found = report.results[0]
+            .violations
+            .iter()
+            .map(|v| format!("{} {}: {}", v.on.clone().unwrap(), v.subject, v.recorded))
+            .collect::<Vec<_>>();
+        let trusted = server_url();
+        assert_eq!(
+            found,
+            vec![
+                format!("preflight from {trusted} vary: Origin"),
+                format!("preflight from {trusted} access-control-allow-methods: GET"),
+                "preflight from https://attacker.example vary: Origin".to_string(),
+                "preflight from https://attacker.example access-control-allow-methods: GET"
+                    .to_string(),
+                "request from https://attacker.example access-control-allow-origin: no wildcard origin with credentials".to_string(),
+                "preflight from null access-control-allow-origin: untrusted origin not allowed with credentials".to_string(),
+                "preflight from null vary: Origin".to_string(),
+                "preflight from null access-control-allow-methods: GET".to_string(),
+            ]
+        );
+    }
+}
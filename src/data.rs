@@ -1,14 +1,36 @@
 use crate::vars::extract;
 use anyhow::anyhow;
 use anyhow::Result as AnyResult;
+use async_trait::async_trait;
 use log::{debug, error};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant, SystemTime};
 use subprocess::{Popen, PopenConfig, Redirection};
 
-pub trait Sender {
+/// Holds a place within a sender's limits until dropped.
+pub type Permit<'s> = Box<dyn Send + 's>;
+
+pub trait Sender: Sync {
     fn send(&self, interaction: &Interaction) -> AnyResult<Response>;
+
+    /// Waits for room within the limits kept on requests to `uri`, for a
+    /// connection made other than by `send`, such as a raw TLS handshake.
+    /// A sender wrapping another asks the one it wraps too, and one that
+    /// never connects refuses.
+    fn permit(&self, _uri: &str) -> AnyResult<Permit<'_>> {
+        Ok(Box::new(()))
+    }
+}
+
+#[async_trait]
+pub trait AsyncSender: Sync {
+    async fn send(&self, interaction: &Interaction) -> AnyResult<Response>;
+
+    /// See `Sender::permit`.
+    async fn permit<'s>(&'s self, _uri: &str) -> AnyResult<Permit<'s>> {
+        Ok(Box::new(()))
+    }
 }
 
 pub struct PrepareOpts {
@@ -66,12 +88,35 @@ fn get_vars_from_cmd(
 
 // fmtstring: {{var}} -> var is being replaced with real name to create the placeholder:
 // ?q={{host}}, -> {{'var'->host}} -> {{host}} -> ?q=v
-fn render_with_vars(text: String, vars: &HashMap<String, String>, fmtstring: &str) -> String {
+pub(crate) fn render_with_vars(
+    text: String,
+    vars: &HashMap<String, String>,
+    fmtstring: &str,
+) -> String {
     vars.iter().fold(text, |acc, (k, v)| {
         acc.replace(fmtstring.replace("var", k).as_str(), v)
     })
 }
 
+// fmtstring: {{var}} -> splits into the open/close braces, every `{{name}}` found in
+// the text is collected: ?q={{host}}&p={{port}} -> [host, port]
+pub(crate) fn placeholders_in(text: &str, fmtstring: &str) -> Vec<String> {
+    let (open, close) = fmtstring.split_once("var").unwrap_or(("{{", "}}"));
+    let mut names = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find(open) {
+        let after = &rest[start + open.len()..];
+        match after.find(close) {
+            Some(end) => {
+                names.push(after[..end].trim().to_string());
+                rest = &after[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    names
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct VarInfo {
     pub expr: Option<String>,
@@ -88,7 +133,7 @@ pub trait Check {
     fn perform(&self, _context: &mut Context, _interaction: &Interaction) -> CheckResult;
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct CheckResult {
     pub request: Request,
     pub response: Option<Response>,
@@ -96,31 +141,256 @@ pub struct CheckResult {
     pub duration: Option<Duration>,
     pub error: Option<String>,
     pub kind: String,
+    pub skipped: Option<String>,
+    pub attempts: Option<u32>,
+    /// The outcome expected of the check, from its interaction's `expect:`
+    /// or a flipped run. A pass when not set.
+    pub expect: Option<Expect>,
+    /// Each response on the way from `http://` to `https://`, for checks
+    /// that follow redirects.
+    #[serde(default)]
+    pub hops: Option<Vec<Hop>>,
 }
+
+/// One response on the way from `http://` to `https://`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Hop {
+    pub uri: String,
+    pub status: u16,
+    /// Where the response redirects to, resolved against `uri`.
+    pub location: Option<String>,
+}
+
+impl Hop {
+    #[must_use]
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.status) && self.location.is_some()
+    }
+}
+
+// a `CheckResult` as serialized, with its `status()` alongside
+#[derive(Serialize)]
+struct SerializedResult<'r> {
+    request: &'r Request,
+    response: &'r Option<Response>,
+    violations: &'r [Violation],
+    duration: &'r Option<Duration>,
+    error: &'r Option<String>,
+    kind: &'r str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: &'r Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attempts: &'r Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expect: &'r Option<Expect>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hops: &'r Option<Vec<Hop>>,
+    status: CheckStatus,
+}
+
+impl Serialize for CheckResult {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedResult {
+            request: &self.request,
+            response: &self.response,
+            violations: &self.violations,
+            duration: &self.duration,
+            error: &self.error,
+            kind: &self.kind,
+            skipped: &self.skipped,
+            attempts: &self.attempts,
+            expect: &self.expect,
+            hops: &self.hops,
+            status: self.status(),
+        }
+        .serialize(serializer)
+    }
+}
+
 impl CheckResult {
-    pub fn invalid_err(kind: &str, interaction: &Interaction, text: &str) -> Self {
+    /// How the check ended, judged against what was expected of it: a
+    /// negative test that ends in the violation it expects has passed.
+    #[must_use]
+    pub fn status(&self) -> CheckStatus {
+        if self.skipped.is_some() {
+            CheckStatus::Skipped
+        } else if self.outcome() == self.expect.unwrap_or(Expect::Pass) {
+            CheckStatus::Passed
+        } else if self.error.is_some() {
+            CheckStatus::Errored
+        } else {
+            CheckStatus::Failed
+        }
+    }
+    /// What the check ended in, to hold against what was expected.
+    #[must_use]
+    pub const fn outcome(&self) -> Expect {
+        if self.error.is_some() {
+            Expect::Error
+        } else if self.violations.is_empty() {
+            Expect::Pass
+        } else {
+            Expect::Violation
+        }
+    }
+    /// A finished check, with nothing skipped, retried or expected of it yet.
+    #[must_use]
+    pub fn new(
+        kind: &str,
+        request: Request,
+        response: Option<Response>,
+        violations: Vec<Violation>,
+        duration: Duration,
+    ) -> Self {
         CheckResult {
-            request: interaction.request.clone(),
-            response: None,
-            violations: vec![],
-            duration: Some(Duration::new(0, 0)),
-            error: Some(text.to_string()),
+            request,
+            response,
+            violations,
+            duration: Some(duration),
+            error: None,
             kind: kind.to_string(),
+            skipped: None,
+            attempts: None,
+            expect: None,
+            hops: None,
+        }
+    }
+    /// The result of a check on `interaction` that either found `violations`,
+    /// in a response when it has one, or stopped at an error.
+    #[must_use]
+    pub fn concluded(
+        kind: &str,
+        interaction: &Interaction,
+        found: AnyResult<(Option<Response>, Vec<Violation>)>,
+        started: Instant,
+    ) -> Self {
+        let request = interaction.request.clone();
+        match found {
+            Ok((response, violations)) => {
+                Self::new(kind, request, response, violations, started.elapsed())
+            }
+            Err(err) => Self::new(kind, request, None, vec![], started.elapsed())
+                .with_error(&err.to_string()),
         }
     }
+    #[must_use]
+    pub fn with_error(mut self, error: &str) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+    pub fn skipped(kind: &str, interaction: &Interaction, reason: &str) -> Self {
+        let mut res = Self::new(
+            kind,
+            interaction.request.clone(),
+            None,
+            vec![],
+            Duration::new(0, 0),
+        );
+        res.skipped = Some(reason.to_string());
+        res
+    }
+    pub fn invalid_err(kind: &str, interaction: &Interaction, text: &str) -> Self {
+        Self::new(
+            kind,
+            interaction.request.clone(),
+            None,
+            vec![],
+            Duration::new(0, 0),
+        )
+        .with_error(text)
+    }
     pub fn invalid(kind: &str, interaction: &Interaction) -> Self {
-        CheckResult {
-            request: interaction.request.clone(),
-            response: None,
-            violations: vec![],
-            duration: Some(Duration::new(0, 0)),
-            error: Some("Invalid check".to_string()),
-            kind: kind.to_string(),
+        Self::invalid_err(kind, interaction, "Invalid check")
+    }
+}
+
+/// How a check ended against its expectation. Unlike `Expect`, it tells
+/// skipped checks apart.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Errored,
+    Skipped,
+}
+
+/// Number of checks in each `CheckStatus`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatusCounts {
+    pub passed: usize,
+    pub failed: usize,
+    pub errored: usize,
+    pub skipped: usize,
+}
+
+impl StatusCounts {
+    pub fn add(&mut self, status: CheckStatus) {
+        match status {
+            CheckStatus::Passed => self.passed += 1,
+            CheckStatus::Failed => self.failed += 1,
+            CheckStatus::Errored => self.errored += 1,
+            CheckStatus::Skipped => self.skipped += 1,
+        }
+    }
+    #[must_use]
+    pub const fn total(&self) -> usize {
+        self.passed + self.failed + self.errored + self.skipped
+    }
+}
+
+/// Aggregates of a run's results, so reporters and callers count them alike.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Summary {
+    pub counts: StatusCounts,
+    pub counts_by_kind: BTreeMap<String, StatusCounts>,
+    pub duration: Duration,
+    pub durations_by_kind: BTreeMap<String, Duration>,
+    /// Ids of the interactions with a failed or errored check, in run order.
+    /// Checks that fail as their interaction expects count as passed.
+    pub failed_ids: Vec<String>,
+}
+
+impl Summary {
+    #[must_use]
+    pub fn from_results(results: &[CheckResult]) -> Self {
+        let mut summary = Self::default();
+        for res in results {
+            let status = res.status();
+            summary.counts.add(status);
+            summary
+                .counts_by_kind
+                .entry(res.kind.clone())
+                .or_default()
+                .add(status);
+            let duration = res.duration.unwrap_or_default();
+            summary.duration += duration;
+            *summary
+                .durations_by_kind
+                .entry(res.kind.clone())
+                .or_default() += duration;
+            let id = res.request.get_id();
+            if matches!(status, CheckStatus::Failed | CheckStatus::Errored)
+                && !summary.failed_ids.contains(&id)
+            {
+                summary.failed_ids.push(id);
+            }
         }
+        summary
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// The outcome an interaction's checks should end in. Negative tests, such as
+/// an auth bypass that must be rejected, expect a violation or an error.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Expect {
+    Pass,
+    Violation,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Violation {
     pub kind: String,
     pub cause: Cause,
@@ -130,7 +400,7 @@ pub struct Violation {
     pub recorded: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Cause {
     WireMissing,
     RecordedMissing,
@@ -138,10 +408,36 @@ pub enum Cause {
     Error,
 }
 
-pub trait ReporterOutput: Sync {
+pub trait ReporterOutput: Send + Sync {
+    fn begin(&mut self, _interactions: &[Interaction]) {}
     fn start(&mut self, _interaction: &Interaction) {}
     fn report(&mut self, _interaction: &Interaction, _check_results: &CheckResult) {}
     fn end(&mut self, _interactions: &[Interaction], _results: &[CheckResult]) {}
+    fn transition(&mut self, _interaction: &Interaction, _transition: &Transition) {}
+}
+
+/// Health of an interaction across the runs of a `Monitor`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Ok,
+    Failing,
+    /// Switching between passing and failing too often to call either way.
+    Flapping,
+}
+
+/// An interaction moving from one `State` to another.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Transition {
+    pub id: String,
+    /// `None` the first time the interaction is seen.
+    pub from: Option<State>,
+    pub to: State,
+    pub at: SystemTime,
+    /// How long the interaction stayed in `from`.
+    pub time_in_state: Option<Duration>,
+    /// Start of the current stretch of failures, unless back to ok.
+    pub first_failure: Option<SystemTime>,
 }
 
 pub type HeaderList = Vec<String>;
@@ -173,16 +469,61 @@ impl Context {
     pub fn new() -> Self {
         Self::default()
     }
+    /// The placeholder format vars are rendered with, `{{var}}` unless configured.
+    #[must_use]
+    pub fn var_format(&self) -> String {
+        self.config
+            .var_braces
+            .clone()
+            .unwrap_or_else(|| "{{var}}".to_string())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Runner {
+    /// Stop the run after the first interaction that fails or errors.
+    #[serde(default)]
     pub exit_on_failure: bool,
+    /// Keep running, but skip interactions that depend on a failed one.
+    #[serde(default)]
+    pub skip_dependents: bool,
+}
+
+/// Another policy file whose interactions a sequence takes in, binding the
+/// given `params` of its interactions.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Include {
+    pub file: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub params: VarsBag,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SequenceInteractions {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<Include>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub setup: Vec<Interaction>,
+    #[serde(default)]
     pub http_interactions: Vec<Interaction>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub teardown: Vec<Interaction>,
+}
+impl SequenceInteractions {
+    pub fn from_yaml(content: &str) -> AnyResult<Self> {
+        let result = serde_yaml::from_str(content)?;
+        Ok(result)
+    }
+    /// Setup, main and teardown interactions, in the order they run.
+    #[must_use]
+    pub fn all_interactions(&self) -> Vec<Interaction> {
+        self.setup
+            .iter()
+            .chain(&self.http_interactions)
+            .chain(&self.teardown)
+            .cloned()
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -198,6 +539,20 @@ pub struct Interaction {
     pub benchmark: Option<Benchmark>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cert: Option<CertificateDetail>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<BTreeMap<String, serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<Retry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<Matrix>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expect: Option<Expect>,
 }
 impl Interaction {
     pub fn sequence_interactions_from_yaml(content: &str) -> AnyResult<Vec<Self>> {
@@ -220,17 +575,31 @@ impl Interaction {
         if self.cert.is_some() {
             v.push("cert");
         }
+        if let Some(checks) = &self.checks {
+            v.extend(checks.keys().map(String::as_str));
+        }
         v
     }
+    /// Tags of the interaction together with those of its request.
+    #[must_use]
+    pub fn tags(&self) -> Vec<&str> {
+        self.tags
+            .iter()
+            .chain(&self.request.tags)
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+    /// Config given to a user-defined check under the `checks:` section.
+    #[must_use]
+    pub fn check_config(&self, name: &str) -> Option<&serde_json::Value> {
+        self.checks.as_ref().and_then(|checks| checks.get(name))
+    }
     pub fn prepare_with(&self, context: &mut Context) -> AnyResult<Self> {
         self.ensure_requirements(context)?;
         let mut res = self.clone();
         let mut req = res.request;
-        let fmtstring = context
-            .config
-            .var_braces
-            .clone()
-            .unwrap_or_else(|| "{{var}}".to_string());
+        let fmtstring = context.var_format();
         let responses = &context.response_bag;
         let response_vars = &context.vars_bag;
 
@@ -303,9 +672,7 @@ impl Interaction {
         Ok(res)
     }
 
-    pub fn send(&self, sender: &dyn Sender) -> AnyResult<Response> {
-        let mut resp = sender.send(self)?;
-
+    fn with_vars(&self, mut resp: Response) -> AnyResult<Response> {
         if let Some(vars) = &self.request.vars {
             let response_vars = extract(&resp, vars)?;
             resp.vars = Some(response_vars);
@@ -313,6 +680,9 @@ impl Interaction {
 
         Ok(resp)
     }
+    pub fn send(&self, sender: &dyn Sender) -> AnyResult<Response> {
+        self.with_vars(sender.send(self)?)
+    }
     pub fn send_with_context(
         &self,
         sender: &dyn Sender,
@@ -355,6 +725,51 @@ pub struct Benchmark {
     pub time_ms: u64,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Matrix {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<VarsBag>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Backoff {
+    #[default]
+    Constant,
+    Linear,
+    Exponential,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Retry {
+    pub attempts: u32,
+    #[serde(default)]
+    pub backoff: Backoff,
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_delay_ms: Option<u64>,
+    /// Keep attempting until the response matches this expectation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<Response>,
+}
+impl Retry {
+    /// How long to wait after the given (1-based) failed attempt.
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let ms = match self.backoff {
+            Backoff::Constant => self.delay_ms,
+            Backoff::Linear => self.delay_ms.saturating_mul(u64::from(attempt)),
+            Backoff::Exponential => self
+                .delay_ms
+                .saturating_mul(2_u64.saturating_pow(attempt.saturating_sub(1))),
+        };
+        Duration::from_millis(self.max_delay_ms.map_or(ms, |max| ms.min(max)))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CertificateDetail {
     pub max_days: u64,
@@ -397,6 +812,10 @@ pub struct Request {
     pub params: Option<Vec<Param>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timeout_ms: Option<u64>,
+    /// Follow redirects to their end, `true` unless set. With `false` the
+    /// redirect response itself is returned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follow_redirects: Option<bool>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub method: Option<String>,
@@ -421,6 +840,8 @@ pub struct Request {
     pub vars_command: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub vars: Option<HashMap<String, VarInfo>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
 }
 impl Request {
     pub fn get_id(&self) -> String {
@@ -428,6 +849,47 @@ impl Request {
             .as_ref()
             .map_or_else(|| "request".to_string(), std::string::ToString::to_string)
     }
+    /// Names of the vars this request renders, in order of appearance.
+    #[must_use]
+    pub fn vars_used(&self, fmtstring: &str) -> Vec<String> {
+        let mut texts = vec![self.uri.clone()];
+        if let Some(uri_list) = &self.uri_list {
+            texts.extend(uri_list.iter().cloned());
+        }
+        if let Some(basic) = &self.basic_auth {
+            texts.push(basic.user.clone());
+            texts.extend(basic.password.iter().cloned());
+        }
+        if let Some(aws) = &self.aws_auth {
+            texts.extend([aws.key.clone(), aws.secret.clone(), aws.service.clone()]);
+            texts.extend(aws.token.iter().cloned());
+            texts.extend(aws.region.iter().cloned());
+        }
+        if let Some(form) = &self.form {
+            texts.extend(form.values().cloned());
+        }
+        if let Some(headers) = &self.headers {
+            texts.extend(headers.values().flatten().cloned());
+        }
+        if let Some(body) = &self.body {
+            texts.push(body.clone());
+        }
+        let mut names = vec![];
+        for name in texts.iter().flat_map(|t| placeholders_in(t, fmtstring)) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+    /// Names of the vars this request captures from its response.
+    #[must_use]
+    pub fn vars_produced(&self) -> Vec<String> {
+        self.vars
+            .as_ref()
+            .map(|vars| vars.keys().cloned().collect())
+            .unwrap_or_default()
+    }
     pub fn get_desc(&self) -> String {
         format!(
             "{} ({})",
@@ -437,7 +899,7 @@ impl Request {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
 pub struct Response {
     pub request_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -451,6 +913,14 @@ pub struct Response {
 }
 
 impl Response {
+    /// The numeric part of `status_code`, such as 301 of "301 Moved Permanently".
+    #[must_use]
+    pub fn status(&self) -> Option<u16> {
+        self.status_code
+            .as_deref()
+            .and_then(|status| status.split_whitespace().next())
+            .and_then(|code| code.parse().ok())
+    }
     pub fn save_vars(&self, context: &mut Context) {
         let vars_bag = &mut context.vars_bag;
         if let Some(vars) = &self.vars {
@@ -44,11 +44,13 @@ impl OpenAPI {
                             id: None,
                             desc: None,
                             timeout_ms: None,
+                            follow_redirects: None,
                             headers: None,
                             body: None,
                             uri_list: None,
                             vars_command: None,
                             vars: None,
+                            tags: None,
                         },
                         response: Some(Response {
                             headers: None,
@@ -61,6 +63,13 @@ impl OpenAPI {
                         benchmark: None,
                         cert: None,
                         examples: None,
+                        checks: None,
+                        depends_on: None,
+                        retry: None,
+                        when: None,
+                        matrix: None,
+                        tags: None,
+                        expect: None,
                     })
                 })
                 .collect::<Vec<_>>(),
@@ -0,0 +1,199 @@
+use crate::data::{CheckResult, Interaction, Permit, ReporterOutput, Request, Response, Sender};
+use anyhow::Result as AnyResult;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{channel, Receiver, Sender as ChannelSender};
+use std::time::{Duration, Instant};
+
+/// Something that happened during a run, owned so it can cross threads.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    SequenceStarted {
+        ids: Vec<String>,
+    },
+    RequestRendered {
+        request: Box<Request>,
+    },
+    ResponseReceived {
+        request_id: String,
+        response: Response,
+        duration: Duration,
+    },
+    RequestFailed {
+        request_id: String,
+        error: String,
+    },
+    CheckFinished {
+        result: Box<CheckResult>,
+    },
+    VarCaptured {
+        request_id: String,
+        name: String,
+        value: String,
+    },
+    RunFinished {
+        results: Vec<CheckResult>,
+    },
+}
+
+/// The sending half of a stream of `RunEvent`s. Give it to a runner with
+/// `with_events` for sequence, check and var events, and to an `EventSender`
+/// for request and response events.
+#[derive(Clone)]
+pub struct RunEvents {
+    tx: ChannelSender<RunEvent>,
+}
+
+impl RunEvents {
+    #[must_use]
+    pub fn channel() -> (Self, Receiver<RunEvent>) {
+        let (tx, rx) = channel();
+        (Self { tx }, rx)
+    }
+
+    // nobody listening is fine, the run goes on
+    fn emit(&self, event: RunEvent) {
+        let _ = self.tx.send(event);
+    }
+}
+
+/// Emits request and response events around another `Sender`.
+#[allow(clippy::module_name_repetitions)]
+pub struct EventSender {
+    inner: Box<dyn Sender>,
+    events: RunEvents,
+}
+
+impl EventSender {
+    #[must_use]
+    pub fn new(inner: Box<dyn Sender>, events: RunEvents) -> Self {
+        Self { inner, events }
+    }
+}
+
+impl Sender for EventSender {
+    fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+        let request_id = inter.request.get_id();
+        self.events.emit(RunEvent::RequestRendered {
+            request: Box::new(inter.request.clone()),
+        });
+        let now = Instant::now();
+        let res = self.inner.send(inter);
+        self.events.emit(match &res {
+            Ok(response) => RunEvent::ResponseReceived {
+                request_id,
+                response: response.clone(),
+                duration: now.elapsed(),
+            },
+            Err(err) => RunEvent::RequestFailed {
+                request_id,
+                error: err.to_string(),
+            },
+        });
+        res
+    }
+
+    fn permit(&self, uri: &str) -> AnyResult<Permit<'_>> {
+        self.inner.permit(uri)
+    }
+}
+
+/// Turns reporter callbacks into events.
+pub(crate) struct EventOutput {
+    events: RunEvents,
+}
+
+impl EventOutput {
+    pub(crate) const fn new(events: RunEvents) -> Self {
+        Self { events }
+    }
+}
+
+impl ReporterOutput for EventOutput {
+    fn begin(&mut self, interactions: &[Interaction]) {
+        self.events.emit(RunEvent::SequenceStarted {
+            ids: interactions.iter().map(|i| i.request.get_id()).collect(),
+        });
+    }
+    fn report(&mut self, _interaction: &Interaction, check_results: &CheckResult) {
+        self.events.emit(RunEvent::CheckFinished {
+            result: Box::new(check_results.clone()),
+        });
+        if let Some(vars) = check_results
+            .response
+            .as_ref()
+            .and_then(|r| r.vars.as_ref())
+        {
+            let mut vars = vars.iter().collect::<Vec<_>>();
+            vars.sort();
+            for (name, value) in vars {
+                self.events.emit(RunEvent::VarCaptured {
+                    request_id: check_results.request.get_id(),
+                    name: name.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+    }
+    fn end(&mut self, _interactions: &[Interaction], results: &[CheckResult]) {
+        self.events.emit(RunEvent::RunFinished {
+            results: results.to_vec(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::SequenceRunner;
+    use crate::sender::ReqwestSender;
+    use mockito::{mock, server_address};
+    use std::collections::HashMap;
+
+    const ITC_SIMPLE: &str = include_str!("fixtures/simple.yaml");
+
+    #[test]
+    fn test_runner_streams_events() {
+        let _m1 = mock("GET", "/one").with_body("next: two").create();
+        let _m2 = mock("GET", "/two").with_body("three").create();
+        let interactions = Interaction::sequence_interactions_from_yaml(ITC_SIMPLE).unwrap();
+        let (events, rx) = RunEvents::channel();
+        let sender = EventSender::new(Box::new(ReqwestSender::new()), events.clone());
+        let runner = SequenceRunner::new(&sender, false, HashMap::new()).with_events(events);
+        let mut ctx = crate::data::Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+
+        assert!(runner.run(&mut ctx, &interactions).ok);
+        drop(runner);
+        drop(sender);
+
+        let kinds = rx
+            .iter()
+            .map(|e| match e {
+                RunEvent::SequenceStarted { .. } => "started".to_string(),
+                RunEvent::RequestRendered { request } => format!("rendered {}", request.uri),
+                RunEvent::ResponseReceived { .. } => "received".to_string(),
+                RunEvent::RequestFailed { .. } => "failed".to_string(),
+                RunEvent::CheckFinished { result } => format!("finished {}", result.kind),
+                RunEvent::VarCaptured { name, value, .. } => format!("var {name}={value}"),
+                RunEvent::RunFinished { results } => format!("done {}", results.len()),
+            })
+            .collect::<Vec<_>>();
+        let host = server_address();
+        assert_eq!(
+            kinds,
+            vec![
+                "started".to_string(),
+                format!("rendered http://{host}/one"),
+                "received".to_string(),
+                "finished content".to_string(),
+                "var foo=two".to_string(),
+                format!("rendered http://{host}/two"),
+                "received".to_string(),
+                "finished content".to_string(),
+                "done 2".to_string(),
+            ]
+        );
+    }
+}
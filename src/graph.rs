@@ -0,0 +1,152 @@
+use crate::data::{placeholders_in, Interaction};
+
+/// For every interaction in a sequence, the indices of the earlier interactions
+/// it takes data from.
+///
+/// An interaction depends on the latest earlier interaction capturing each var
+/// it renders or tests in `when:`, and on every earlier interaction named in
+/// its `depends_on`. A `vars_command` reads all previous responses, so it
+/// depends on everything before it.
+#[must_use]
+pub fn data_dependencies(sequence: &[Interaction], fmtstring: &str) -> Vec<Vec<usize>> {
+    sequence
+        .iter()
+        .enumerate()
+        .map(|(idx, interaction)| {
+            let earlier = &sequence[..idx];
+            if interaction.request.vars_command.is_some() {
+                return (0..idx).collect();
+            }
+            let mut used = interaction.request.vars_used(fmtstring);
+            if let Some(when) = &interaction.when {
+                used.extend(placeholders_in(when, fmtstring));
+            }
+            let mut deps = used
+                .iter()
+                .filter_map(|var| {
+                    earlier
+                        .iter()
+                        .rposition(|e| e.request.vars_produced().contains(var))
+                })
+                .collect::<Vec<_>>();
+            if let Some(depends_on) = &interaction.depends_on {
+                deps.extend(
+                    earlier
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, e)| depends_on.contains(&e.request.get_id()))
+                        .map(|(i, _)| i),
+                );
+            }
+            deps.sort_unstable();
+            deps.dedup();
+            deps
+        })
+        .collect()
+}
+
+/// For every interaction in a sequence, the indices of the earlier interactions
+/// it has to wait for: its `data_dependencies`, and, for one capturing a var,
+/// every earlier interaction capturing or using that var, so the var is
+/// overwritten in sequence order.
+#[must_use]
+pub fn dependencies(sequence: &[Interaction], fmtstring: &str) -> Vec<Vec<usize>> {
+    data_dependencies(sequence, fmtstring)
+        .into_iter()
+        .zip(sequence)
+        .enumerate()
+        .map(|(idx, (mut deps, interaction))| {
+            let produced = interaction.request.vars_produced();
+            deps.extend(sequence[..idx].iter().enumerate().filter_map(|(i, e)| {
+                let mut touched = e.request.vars_produced();
+                touched.extend(e.request.vars_used(fmtstring));
+                if let Some(when) = &e.when {
+                    touched.extend(placeholders_in(when, fmtstring));
+                }
+                produced
+                    .iter()
+                    .any(|var| touched.contains(var))
+                    .then_some(i)
+            }));
+            deps.sort_unstable();
+            deps.dedup();
+            deps
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependencies_from_vars_and_depends_on() {
+        let sequence = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: login
+    uri: http://{{host}}/login
+    vars:
+      token:
+        from: /body
+- request:
+    id: health
+    uri: http://{{host}}/health
+- request:
+    id: profile
+    uri: http://{{host}}/me
+    headers:
+      Authorization:
+      - Bearer {{token}}
+- request:
+    id: logout
+    uri: http://{{host}}/logout
+  depends_on:
+  - health
+  - profile
+"#,
+        )
+        .unwrap();
+        let deps = dependencies(&sequence, "{{var}}");
+        assert_eq!(deps, vec![vec![], vec![], vec![0], vec![1, 2]]);
+    }
+
+    #[test]
+    fn test_dependencies_keep_captures_of_one_var_in_order() {
+        let sequence = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: login_a
+    uri: http://{{host}}/a
+    vars:
+      token:
+        from: /body
+- request:
+    id: use_a
+    uri: http://{{host}}/me
+    headers:
+      Authorization:
+      - Bearer {{token}}
+- request:
+    id: login_b
+    uri: http://{{host}}/b
+    vars:
+      token:
+        from: /body
+- request:
+    id: use_b
+    uri: http://{{host}}/me
+    headers:
+      Authorization:
+      - Bearer {{token}}
+"#,
+        )
+        .unwrap();
+        let deps = dependencies(&sequence, "{{var}}");
+        assert_eq!(deps, vec![vec![], vec![0], vec![0, 1], vec![2]]);
+        let deps = data_dependencies(&sequence, "{{var}}");
+        assert_eq!(deps, vec![vec![], vec![0], vec![], vec![2]]);
+    }
+}
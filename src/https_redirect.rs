@@ -0,0 +1,453 @@
+use anyhow::{anyhow, Result as AnyResult};
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::auth_enforcement::AuthPolicy;
+use crate::data::{
+    Cause, Check, CheckResult, Context, Hop, Interaction, Response, Sender, Violation,
+};
+use crate::registry::CheckConfig;
+use crate::security_headers::{header, hsts_violations};
+pub const NAME: &str = "https_redirect";
+
+fn max_hops() -> usize {
+    10
+}
+
+fn hsts_min_max_age() -> Option<u64> {
+    Some(31_536_000)
+}
+
+/// What the `https_redirect` check expects of the plain `http://` variant of
+/// an `https://` interaction, and of HSTS on the `https://` response:
+///
+/// ```yaml
+/// checks:
+///   https_redirect:
+///     hsts_min_max_age: 31536000
+///     hsts_include_subdomains: true
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RedirectPolicy {
+    /// Port plain http is served on, 80 unless given.
+    #[serde(default)]
+    pub http_port: Option<u16>,
+    #[serde(default = "max_hops")]
+    pub max_hops: usize,
+    /// Minimum HSTS max-age in seconds, `null` to not check HSTS.
+    #[serde(default = "hsts_min_max_age")]
+    pub hsts_min_max_age: Option<u64>,
+    #[serde(default)]
+    pub hsts_include_subdomains: bool,
+}
+
+impl CheckConfig for RedirectPolicy {}
+
+/// Sends `prepared` to `start` without following redirects, then to every
+/// plain `http://` location it is redirected to, up to `max_hops` requests.
+/// Credentials are never sent over plain `http://`, so they are stripped from
+/// every hop.
+pub fn follow(
+    sender: &dyn Sender,
+    prepared: &Interaction,
+    start: Url,
+    max_hops: usize,
+) -> AnyResult<Vec<Hop>> {
+    let stripped = AuthPolicy::default().stripped(prepared);
+    let mut hops = vec![];
+    let mut uri = start;
+    while hops.len() < max_hops {
+        let mut probe = stripped.clone();
+        probe.request.uri = uri.to_string();
+        probe.request.follow_redirects = Some(false);
+        let resp = sender.send(&probe)?;
+        let location = header(resp.headers.as_ref().unwrap_or(&HashMap::new()), "location")
+            .and_then(|location| uri.join(location.trim()).ok());
+        let hop = Hop {
+            uri: uri.to_string(),
+            status: resp.status().unwrap_or_default(),
+            location: location.as_ref().map(ToString::to_string),
+        };
+        let next = location.filter(|next| hop.is_redirect() && next.scheme() == "http");
+        hops.push(hop);
+        match next {
+            Some(next) => uri = next,
+            None => break,
+        }
+    }
+    Ok(hops)
+}
+
+fn violation(
+    cause: Cause,
+    subject: &str,
+    on: String,
+    wire: Option<String>,
+    recorded: &str,
+) -> Violation {
+    Violation {
+        kind: NAME.to_string(),
+        cause,
+        subject: subject.to_string(),
+        on: Some(on),
+        wire,
+        recorded: recorded.to_string(),
+    }
+}
+
+/// Every hop that is not a permanent redirect, and where the chain fails to
+/// end up at `target`, with the same origin, path and query.
+#[must_use]
+pub fn hop_violations(hops: &[Hop], target: &Url) -> Vec<Violation> {
+    let on = |i: usize, hop: &Hop| format!("hop {}: {}", i + 1, hop.uri);
+    let mut violations = vec![];
+    for (i, hop) in hops.iter().enumerate() {
+        if hop.is_redirect() && ![301, 308].contains(&hop.status) {
+            violations.push(violation(
+                Cause::Mismatch,
+                "redirect",
+                on(i, hop),
+                Some(format!(
+                    "{} -> {}",
+                    hop.status,
+                    hop.location.as_deref().unwrap_or_default()
+                )),
+                "301 or 308",
+            ));
+        }
+    }
+    let Some(last) = hops.last() else {
+        return violations;
+    };
+    let location = last
+        .location
+        .as_deref()
+        .filter(|_| last.is_redirect())
+        .and_then(|location| Url::parse(location).ok());
+    match location {
+        None => violations.push(violation(
+            Cause::WireMissing,
+            "redirect",
+            on(hops.len() - 1, last),
+            Some(last.status.to_string()),
+            "redirect to https://",
+        )),
+        Some(location) if location.scheme() != "https" => violations.push(violation(
+            Cause::Mismatch,
+            "redirect",
+            on(hops.len() - 1, last),
+            Some(location.to_string()),
+            &format!("https:// within {} hops", hops.len()),
+        )),
+        Some(location)
+            if location.origin() != target.origin()
+                || location.path() != target.path()
+                || location.query() != target.query() =>
+        {
+            violations.push(violation(
+                Cause::Mismatch,
+                "location",
+                on(hops.len() - 1, last),
+                Some(location.to_string()),
+                target.as_str(),
+            ));
+        }
+        Some(_) => {}
+    }
+    violations
+}
+
+/// Sends an `https://` interaction over plain `http://`, expecting permanent
+/// redirects to the same `https://` uri, and checks HSTS on the `https://`
+/// response. Every `Hop` is on its result.
+pub struct HttpsRedirect<'a> {
+    sender: &'a dyn Sender,
+}
+
+impl<'a> HttpsRedirect<'a> {
+    pub fn new(sender: &'a dyn Sender) -> Self {
+        Self { sender }
+    }
+
+    fn probe(
+        &self,
+        context: &mut Context,
+        interaction: &Interaction,
+        policy: &RedirectPolicy,
+    ) -> AnyResult<(Vec<Hop>, Option<Response>, Vec<Violation>)> {
+        let prepared = interaction.prepare_with(context)?;
+        let target = Url::parse(&prepared.request.uri)?;
+        if target.scheme() != "https" {
+            return Err(anyhow!(
+                "{} needs an https:// uri, got '{}'",
+                NAME,
+                prepared.request.uri
+            ));
+        }
+        let mut start = target.clone();
+        start
+            .set_scheme("http")
+            .and_then(|()| start.set_port(policy.http_port))
+            .map_err(|()| anyhow!("cannot make an http:// uri of '{}'", target))?;
+
+        let hops = follow(self.sender, &prepared, start, policy.max_hops.max(1))?;
+        let mut violations = hop_violations(&hops, &target);
+
+        let Some(min_max_age) = policy.hsts_min_max_age else {
+            return Ok((hops, None, violations));
+        };
+        let resp = self.sender.send(&prepared)?;
+        violations.extend(
+            hsts_violations(
+                header(
+                    resp.headers.as_ref().unwrap_or(&HashMap::new()),
+                    "strict-transport-security",
+                ),
+                min_max_age,
+                policy.hsts_include_subdomains,
+            )
+            .into_iter()
+            .map(|v| Violation {
+                kind: NAME.to_string(),
+                ..v
+            }),
+        );
+        Ok((hops, Some(resp), violations))
+    }
+}
+
+impl<'a> Check for HttpsRedirect<'a> {
+    fn name(&self) -> &str {
+        NAME
+    }
+    fn perform(&self, context: &mut Context, interaction: &Interaction) -> CheckResult {
+        let now = Instant::now();
+        let mut hops = None;
+        let found =
+            RedirectPolicy::from_config(interaction.check_config(NAME)).and_then(|policy| {
+                let (chain, response, violations) = self.probe(context, interaction, &policy)?;
+                hops = Some(chain);
+                Ok((response, violations))
+            });
+        let mut res = CheckResult::concluded(NAME, interaction, found, now);
+        res.hops = hops;
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sender::ReqwestSender;
+    use mockito::{mock, server_url};
+    use std::sync::Mutex;
+
+    type Route = (&'static str, Vec<(&'static str, &'static str)>);
+
+    /// Responds from a fixed table of uris, keeping every request sent.
+    struct CannedSender {
+        routes: HashMap<String, Route>,
+        sent: Mutex<Vec<Interaction>>,
+    }
+
+    impl CannedSender {
+        fn new(routes: Vec<(&str, Route)>) -> Self {
+            Self {
+                routes: routes
+                    .into_iter()
+                    .map(|(uri, route)| (uri.to_string(), route))
+                    .collect(),
+                sent: Mutex::default(),
+            }
+        }
+    }
+
+    impl Sender for CannedSender {
+        fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+            self.sent.lock().unwrap().push(inter.clone());
+            let (status, headers) = self
+                .routes
+                .get(&inter.request.uri)
+                .ok_or_else(|| anyhow!("no route to {}", inter.request.uri))?;
+            Ok(Response {
+                request_id: Some(inter.request.get_id()),
+                status_code: Some(status.to_string()),
+                headers: Some(
+                    headers
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), vec![v.to_string()]))
+                        .collect(),
+                ),
+                body: None,
+                vars: None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_https_redirect_reports_each_hop() {
+        let sender = CannedSender::new(vec![
+            (
+                "http://example.com/account?x=1",
+                (
+                    "302 Found",
+                    vec![("location", "http://www.example.com/account?x=1")],
+                ),
+            ),
+            (
+                "http://www.example.com/account?x=1",
+                (
+                    "301 Moved Permanently",
+                    vec![("location", "https://www.example.com/account?x=1")],
+                ),
+            ),
+            (
+                "https://example.com/account?x=1",
+                ("200 OK", vec![("strict-transport-security", "max-age=300")]),
+            ),
+            (
+                "http://good.example/",
+                ("308", vec![("location", "https://good.example/")]),
+            ),
+            (
+                "https://good.example/",
+                (
+                    "200 OK",
+                    vec![("strict-transport-security", "max-age=31536000")],
+                ),
+            ),
+        ]);
+        let check = HttpsRedirect::new(&sender);
+        let perform = |uri: &str| {
+            let inter = Interaction::from_yaml(&format!(
+                "request:\n  uri: {uri}\nchecks:\n  https_redirect: {{}}\n"
+            ))
+            .unwrap();
+            check.perform(&mut Context::new(), &inter)
+        };
+
+        let res = perform("https://example.com/account?x=1");
+        assert_eq!(
+            res.hops.unwrap(),
+            vec![
+                Hop {
+                    uri: "http://example.com/account?x=1".to_string(),
+                    status: 302,
+                    location: Some("http://www.example.com/account?x=1".to_string()),
+                },
+                Hop {
+                    uri: "http://www.example.com/account?x=1".to_string(),
+                    status: 301,
+                    location: Some("https://www.example.com/account?x=1".to_string()),
+                },
+            ]
+        );
+        let found = res
+            .violations
+            .iter()
+            .map(|v| format!("{} {}: {}", v.on.clone().unwrap(), v.subject, v.recorded))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                "hop 1: http://example.com/account?x=1 redirect: 301 or 308",
+                "hop 2: http://www.example.com/account?x=1 location: https://example.com/account?x=1",
+                "strict-transport-security headers: max-age >= 31536000",
+            ]
+        );
+
+        let res = perform("https://good.example/");
+        assert_eq!(res.error, None);
+        assert!(res.violations.is_empty());
+        assert_eq!(res.response.unwrap().status(), Some(200));
+        let serialized = serde_json::to_value(&res.hops).unwrap();
+        assert_eq!(
+            serialized,
+            serde_json::json!([{
+                "uri": "http://good.example/",
+                "status": 308,
+                "location": "https://good.example/",
+            }])
+        );
+    }
+
+    #[test]
+    fn test_https_redirect_sends_no_credentials_over_http() {
+        let sender = CannedSender::new(vec![
+            (
+                "http://good.example/",
+                ("308", vec![("location", "https://good.example/")]),
+            ),
+            (
+                "https://good.example/",
+                (
+                    "200 OK",
+                    vec![("strict-transport-security", "max-age=31536000")],
+                ),
+            ),
+        ]);
+        let inter = Interaction::from_yaml(
+            r#"
+request:
+  uri: https://good.example/
+  basic_auth:
+    user: admin
+    password: secret
+  headers:
+    Authorization:
+    - Bearer abc
+    Cookie:
+    - session=s1
+    Accept:
+    - text/html
+checks:
+  https_redirect: {}
+"#,
+        )
+        .unwrap();
+
+        let res = HttpsRedirect::new(&sender).perform(&mut Context::new(), &inter);
+
+        assert_eq!(res.error, None);
+        let sent = sender.sent.lock().unwrap();
+        let credentials = sent
+            .iter()
+            .map(|inter| {
+                let headers = inter.request.headers.clone().unwrap_or_default();
+                let mut names = headers.keys().cloned().collect::<Vec<_>>();
+                names.sort();
+                format!(
+                    "{} basic_auth={} {}",
+                    inter.request.uri,
+                    inter.request.basic_auth.is_some(),
+                    names.join(",")
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            credentials,
+            vec![
+                "http://good.example/ basic_auth=false Accept",
+                "https://good.example/ basic_auth=true Accept,Authorization,Cookie",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reqwest_sender_can_stop_at_redirects() {
+        let _m = mock("GET", "/moved")
+            .with_status(301)
+            .with_header("location", "/elsewhere")
+            .create();
+        let mut inter =
+            Interaction::from_yaml(&format!("request:\n  uri: {}/moved\n", server_url())).unwrap();
+        inter.request.follow_redirects = Some(false);
+
+        let resp = ReqwestSender::new().send(&inter).unwrap();
+
+        assert_eq!(resp.status_code.as_deref(), Some("301 Moved Permanently"));
+    }
+}
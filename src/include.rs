@@ -0,0 +1,222 @@
+use crate::data::{render_with_vars, Include, Interaction, SequenceInteractions, VarsBag};
+use anyhow::{anyhow, Context as _, Result as AnyResult};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn render_value(value: &mut Value, vars: &VarsBag, fmtstring: &str) {
+    match value {
+        Value::String(text) => *text = render_with_vars(std::mem::take(text), vars, fmtstring),
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|v| render_value(v, vars, fmtstring)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|v| render_value(v, vars, fmtstring)),
+        _ => {}
+    }
+}
+
+// renders the params into an interaction, which then no longer requires them
+fn bind(interaction: &Interaction, params: &VarsBag, fmtstring: &str) -> AnyResult<Interaction> {
+    let mut value = serde_json::to_value(interaction)?;
+    render_value(&mut value, params, fmtstring);
+    let mut bound: Interaction = serde_json::from_value(value)?;
+    bound.request.params = bound
+        .request
+        .params
+        .map(|declared| {
+            declared
+                .into_iter()
+                .filter(|p| !params.contains_key(&p.name))
+                .collect::<Vec<_>>()
+        })
+        .filter(|declared| !declared.is_empty());
+    Ok(bound)
+}
+
+fn bind_all(
+    interactions: &[Interaction],
+    params: &VarsBag,
+    fmtstring: &str,
+) -> AnyResult<Vec<Interaction>> {
+    interactions
+        .iter()
+        .map(|i| bind(i, params, fmtstring))
+        .collect()
+}
+
+fn bind_include(
+    include: &Include,
+    sequence: &SequenceInteractions,
+    fmtstring: &str,
+) -> AnyResult<SequenceInteractions> {
+    let declared = sequence
+        .all_interactions()
+        .iter()
+        .flat_map(|i| i.request.params.clone().unwrap_or_default())
+        .map(|p| p.name)
+        .collect::<BTreeSet<_>>();
+    if let Some(unknown) = include.params.keys().find(|k| !declared.contains(*k)) {
+        return Err(anyhow!(
+            "include '{}' has no param '{unknown}' (declared: {})",
+            include.file,
+            declared.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    Ok(SequenceInteractions {
+        include: vec![],
+        setup: bind_all(&sequence.setup, &include.params, fmtstring)?,
+        http_interactions: bind_all(&sequence.http_interactions, &include.params, fmtstring)?,
+        teardown: bind_all(&sequence.teardown, &include.params, fmtstring)?,
+    })
+}
+
+// matrix files are relative to the policy that names them, like includes
+fn resolve_matrix_files(sequence: &mut SequenceInteractions, dir: &Path) {
+    let interactions = sequence
+        .setup
+        .iter_mut()
+        .chain(sequence.http_interactions.iter_mut())
+        .chain(sequence.teardown.iter_mut());
+    for interaction in interactions {
+        if let Some(file) = interaction.matrix.as_mut().and_then(|m| m.file.as_mut()) {
+            *file = dir.join(&*file).display().to_string();
+        }
+    }
+}
+
+fn load(path: &Path, fmtstring: &str, stack: &mut Vec<PathBuf>) -> AnyResult<SequenceInteractions> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("cannot read policy '{}'", path.display()))?;
+    if let Some(pos) = stack.iter().position(|p| *p == canonical) {
+        let cycle = stack[pos..]
+            .iter()
+            .chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        return Err(anyhow!("include cycle: {}", cycle));
+    }
+    let content = fs::read_to_string(&canonical)
+        .with_context(|| format!("cannot read policy '{}'", path.display()))?;
+    let mut sequence = SequenceInteractions::from_yaml(&content)
+        .with_context(|| format!("cannot parse policy '{}'", path.display()))?;
+    let dir = canonical
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    resolve_matrix_files(&mut sequence, &dir);
+
+    stack.push(canonical);
+    let mut merged = SequenceInteractions {
+        include: vec![],
+        setup: vec![],
+        http_interactions: vec![],
+        teardown: vec![],
+    };
+    let mut included_teardown = vec![];
+    for include in &sequence.include {
+        let included = load(&dir.join(&include.file), fmtstring, stack)?;
+        let included = bind_include(include, &included, fmtstring)?;
+        merged.setup.extend(included.setup);
+        merged.http_interactions.extend(included.http_interactions);
+        included_teardown.extend(included.teardown);
+    }
+    stack.pop();
+
+    merged.setup.extend(sequence.setup);
+    merged.http_interactions.extend(sequence.http_interactions);
+    merged.teardown.extend(sequence.teardown);
+    merged.teardown.extend(included_teardown);
+    Ok(merged)
+}
+
+impl SequenceInteractions {
+    /// Reads a sequence from a policy file, taking in the files it lists under
+    /// `include:`. Included paths, and `matrix:` files, are relative to the
+    /// file that names them.
+    ///
+    /// Included interactions run before the file's own, in each phase, except
+    /// for teardown where the file's own run first. Params given to an include
+    /// are rendered with `fmtstring` into its interactions and no longer
+    /// required from the context; each must be declared by one of them.
+    pub fn from_file(path: impl AsRef<Path>, fmtstring: &str) -> AnyResult<Self> {
+        load(path.as_ref(), fmtstring, &mut vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/fixtures/include")
+            .join(name)
+    }
+
+    #[test]
+    fn test_from_file_binds_included_params() {
+        let sequence = SequenceInteractions::from_file(fixture("main.yaml"), "{{var}}").unwrap();
+        let ids = |interactions: &[Interaction]| {
+            interactions
+                .iter()
+                .map(|i| i.request.get_id())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&sequence.http_interactions), vec!["login", "profile"]);
+        assert_eq!(ids(&sequence.teardown), vec!["cleanup", "logout"]);
+
+        let login = &sequence.http_interactions[0].request;
+        assert_eq!(login.uri, "http://{{host}}/v2/login");
+        assert_eq!(
+            login
+                .params
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["user"]
+        );
+    }
+
+    #[test]
+    fn test_from_file_resolves_matrix_files_next_to_their_policy() {
+        let sequence = SequenceInteractions::from_file(fixture("matrix.yaml"), "{{var}}").unwrap();
+        let rows = sequence.http_interactions[0]
+            .matrix
+            .as_ref()
+            .unwrap()
+            .rows()
+            .unwrap();
+        assert_eq!(
+            rows.iter()
+                .map(|r| r["tenant"].as_str())
+                .collect::<Vec<_>>(),
+            vec!["acme", "globex"]
+        );
+    }
+
+    #[test]
+    fn test_from_file_rejects_unknown_params_and_cycles() {
+        let err = SequenceInteractions::from_file(fixture("unknown-param.yaml"), "{{var}}")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "include 'shared/auth.yaml' has no param 'password' (declared: login_path, user)"
+        );
+
+        let err = SequenceInteractions::from_file(fixture("cycle-a.yaml"), "{{var}}")
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("include cycle: "));
+        assert!(err.contains("cycle-a.yaml -> "));
+        assert!(err.contains("cycle-b.yaml -> "));
+        assert!(err.ends_with("cycle-a.yaml"));
+    }
+}
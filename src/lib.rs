@@ -1,14 +1,35 @@
 extern crate log;
 extern crate reqwest;
+pub mod async_runner;
+pub mod auth_enforcement;
 pub mod bench;
+pub mod cassette;
 pub mod cert;
+pub mod checkpoint;
+pub mod compare;
+pub mod condition;
 pub mod content;
+pub mod cookies;
+pub mod cors;
 pub mod data;
 pub mod discovery;
+pub mod events;
+pub mod graph;
+pub mod https_redirect;
+pub mod include;
 pub mod matcher;
+pub mod matrix;
+pub mod monitor;
+pub mod parallel;
+pub mod rate_limit;
+pub mod registry;
 pub mod reporters;
 pub mod runner;
+pub mod security_headers;
+pub mod selection;
 pub mod sender;
+#[cfg(feature = "tls")]
+pub mod tls;
 pub mod vars;
 
 #[cfg(test)]
@@ -0,0 +1,191 @@
+use crate::data::{Interaction, Matrix, VarsBag};
+use anyhow::{anyhow, Context as _, Result as AnyResult};
+use std::fs;
+use std::path::Path;
+
+/// One run of an interaction: a matrix row and/or a single `uri_list` entry.
+pub struct Iteration {
+    pub interaction: Interaction,
+    pub vars: VarsBag,
+}
+
+fn value_to_string(value: &serde_json::Value) -> String {
+    value
+        .as_str()
+        .map_or_else(|| value.to_string(), ToString::to_string)
+}
+
+fn rows_from_csv(content: &str) -> AnyResult<Vec<VarsBag>> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    reader
+        .records()
+        .map(|record| {
+            let record = record?;
+            Ok(headers
+                .iter()
+                .zip(record.iter())
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect())
+        })
+        .collect()
+}
+
+fn rows_from_jsonl(content: &str) -> AnyResult<Vec<VarsBag>> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let row: serde_json::Map<String, serde_json::Value> = serde_json::from_str(line)?;
+            Ok(row
+                .iter()
+                .map(|(k, v)| (k.clone(), value_to_string(v)))
+                .collect())
+        })
+        .collect()
+}
+
+impl Matrix {
+    /// Every row of vars, inline `values` first and then the rows of `file`
+    /// (`.csv` with a header row, or `.jsonl` with one object per line).
+    pub fn rows(&self) -> AnyResult<Vec<VarsBag>> {
+        let mut rows = self.values.clone().unwrap_or_default();
+        if let Some(file) = &self.file {
+            let content =
+                fs::read_to_string(file).with_context(|| format!("cannot read matrix '{file}'"))?;
+            let extension = Path::new(file)
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            rows.extend(match extension {
+                "csv" => rows_from_csv(&content)?,
+                "jsonl" | "ndjson" => rows_from_jsonl(&content)?,
+                _ => return Err(anyhow!("unsupported matrix file '{}'", file)),
+            });
+        }
+        Ok(rows)
+    }
+}
+
+/// Expands an interaction into one iteration per matrix row and `uri_list`
+/// entry. Each iteration gets its position appended to the request id, e.g.
+/// `login[2]`, or `login[2][0]` for a row combined with a uri.
+pub fn expand(interaction: &Interaction) -> AnyResult<Vec<Iteration>> {
+    let rows = match &interaction.matrix {
+        Some(matrix) => matrix
+            .rows()?
+            .into_iter()
+            .enumerate()
+            .map(|(i, row)| (Some(i), row))
+            .collect(),
+        None => vec![(None, VarsBag::new())],
+    };
+    let uris = interaction.request.uri_list.as_ref().map_or_else(
+        || vec![(None, interaction.request.uri.clone())],
+        |uri_list| {
+            uri_list
+                .iter()
+                .enumerate()
+                .map(|(j, uri)| (Some(j), uri.clone()))
+                .collect()
+        },
+    );
+
+    let mut iterations = vec![];
+    for (row_idx, row) in &rows {
+        for (uri_idx, uri) in &uris {
+            let mut expanded = interaction.clone();
+            if row_idx.is_some() || uri_idx.is_some() {
+                let suffix = [row_idx, uri_idx]
+                    .iter()
+                    .filter_map(|i| i.map(|i| format!("[{i}]")))
+                    .collect::<String>();
+                expanded.request.id = Some(format!("{}{suffix}", interaction.request.get_id()));
+            }
+            expanded.request.uri = uri.clone();
+            expanded.request.uri_list = None;
+            expanded.matrix = None;
+            iterations.push(Iteration {
+                interaction: expanded,
+                vars: row.clone(),
+            });
+        }
+    }
+    Ok(iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_matrix_and_uri_list() {
+        let interaction = Interaction::from_yaml(
+            r#"
+request:
+  id: tenant
+  uri: unused
+  uri_list:
+  - http://{{host}}/a/{{tenant}}
+  - http://{{host}}/b/{{tenant}}
+matrix:
+  values:
+  - tenant: acme
+  - tenant: globex
+"#,
+        )
+        .unwrap();
+        let iterations = expand(&interaction).unwrap();
+        assert_eq!(
+            iterations
+                .iter()
+                .map(|i| (
+                    i.interaction.request.get_id(),
+                    i.interaction.request.uri.clone(),
+                    i.vars["tenant"].clone()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "tenant[0][0]".to_string(),
+                    "http://{{host}}/a/{{tenant}}".to_string(),
+                    "acme".to_string()
+                ),
+                (
+                    "tenant[0][1]".to_string(),
+                    "http://{{host}}/b/{{tenant}}".to_string(),
+                    "acme".to_string()
+                ),
+                (
+                    "tenant[1][0]".to_string(),
+                    "http://{{host}}/a/{{tenant}}".to_string(),
+                    "globex".to_string()
+                ),
+                (
+                    "tenant[1][1]".to_string(),
+                    "http://{{host}}/b/{{tenant}}".to_string(),
+                    "globex".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matrix_rows_from_files() {
+        assert_eq!(
+            rows_from_csv("user,role\njoe,admin\n\"doe, jane\",viewer\n").unwrap(),
+            vec![
+                maplit::hashmap! {"user".to_string() => "joe".to_string(), "role".to_string() => "admin".to_string()},
+                maplit::hashmap! {"user".to_string() => "doe, jane".to_string(), "role".to_string() => "viewer".to_string()},
+            ]
+        );
+        assert_eq!(
+            rows_from_jsonl("{\"user\":\"joe\",\"age\":3}\n\n{\"user\":\"ann\",\"age\":4}\n")
+                .unwrap(),
+            vec![
+                maplit::hashmap! {"user".to_string() => "joe".to_string(), "age".to_string() => "3".to_string()},
+                maplit::hashmap! {"user".to_string() => "ann".to_string(), "age".to_string() => "4".to_string()},
+            ]
+        );
+    }
+}
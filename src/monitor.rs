@@ -0,0 +1,340 @@
+use crate::data::{
+    CheckResult, Context, Interaction, ReporterConfig, SequenceInteractions, State, Transition,
+};
+use crate::reporters::{create_reporter, Reporter};
+use crate::runner::{RunOptions, SequenceRunner};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+/// What the monitor remembers about one interaction between runs.
+struct Tracked {
+    state: State,
+    since: SystemTime,
+    first_failure: Option<SystemTime>,
+    // latest outcomes, newest last
+    outcomes: VecDeque<bool>,
+}
+
+/// Re-runs a sequence on an interval, keeping the state of every interaction
+/// (ok, failing or flapping) and reporting only when it changes.
+///
+/// An interaction is flapping when its outcome changed at least
+/// `flap_threshold` times within its last `flap_window` runs. Each iteration
+/// of a `matrix:` or `uri_list` is tracked on its own, and interactions that
+/// were skipped keep their state.
+pub struct Monitor<'a> {
+    runner: SequenceRunner<'a>,
+    reporters: HashMap<String, ReporterConfig>,
+    interval: Duration,
+    flap_window: usize,
+    flap_threshold: usize,
+    states: BTreeMap<String, Tracked>,
+}
+
+impl<'a> Monitor<'a> {
+    /// Takes over the reporters of `runner`, which are then called with
+    /// transitions instead of results.
+    #[must_use]
+    pub fn new(mut runner: SequenceRunner<'a>, interval: Duration) -> Self {
+        let reporters = runner.take_reporters();
+        Self {
+            runner,
+            reporters,
+            interval,
+            flap_window: 10,
+            flap_threshold: 4,
+            states: BTreeMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn from_opts(run_opts: &'a RunOptions, interval: Duration) -> Self {
+        Self::new(SequenceRunner::from_opts(run_opts), interval)
+    }
+
+    #[must_use]
+    pub fn with_flapping(mut self, window: usize, threshold: usize) -> Self {
+        self.flap_window = window.max(2);
+        self.flap_threshold = threshold.max(1);
+        self
+    }
+
+    /// Current state of every interaction seen so far, by request id.
+    #[must_use]
+    pub fn states(&self) -> BTreeMap<String, State> {
+        self.states
+            .iter()
+            .map(|(id, tracked)| (id.clone(), tracked.state))
+            .collect()
+    }
+
+    /// Runs the sequence once, on a copy of `context`, and reports the
+    /// transitions it caused.
+    pub fn tick(&mut self, context: &Context, sequence: &SequenceInteractions) -> Vec<Transition> {
+        let mut reporter = create_reporter(&self.reporters);
+        self.tick_into(context, sequence, &mut reporter)
+    }
+
+    fn tick_into(
+        &mut self,
+        context: &Context,
+        sequence: &SequenceInteractions,
+        reporter: &mut Reporter<'_>,
+    ) -> Vec<Transition> {
+        let mut ctx = context.clone();
+        let report = self.runner.run_sequence(&mut ctx, sequence);
+        let now = SystemTime::now();
+
+        // results grouped by the id they ran under, in the order they ran
+        let mut grouped: Vec<(String, Vec<&CheckResult>)> = vec![];
+        for res in &report.results {
+            let id = res.request.get_id();
+            match grouped.iter_mut().find(|(seen, _)| *seen == id) {
+                Some((_, results)) => results.push(res),
+                None => grouped.push((id, vec![res])),
+            }
+        }
+
+        let all = sequence.all_interactions();
+        let mut transitions = vec![];
+        for (id, results) in grouped {
+            if results.iter().all(|r| r.skipped.is_some()) {
+                continue;
+            }
+            let ok = self.runner.settings().passed(results.iter().copied());
+            if let Some(transition) = self.observe(&id, ok, now) {
+                if let Some(interaction) = all
+                    .iter()
+                    .find(|i| {
+                        let base = i.request.get_id();
+                        id == base || id.starts_with(&format!("{base}["))
+                    })
+                    .map(|i| Interaction {
+                        request: results[0].request.clone(),
+                        ..i.clone()
+                    })
+                {
+                    reporter.transition(&interaction, &transition);
+                }
+                transitions.push(transition);
+            }
+        }
+        transitions
+    }
+
+    /// Ticks every `interval`, `times` times or forever when `None`.
+    pub fn run(
+        &mut self,
+        context: &Context,
+        sequence: &SequenceInteractions,
+        times: Option<usize>,
+    ) {
+        let mut done = 0;
+        loop {
+            self.tick(context, sequence);
+            done += 1;
+            if times.is_some_and(|times| done >= times) {
+                break;
+            }
+            sleep(self.interval);
+        }
+    }
+
+    fn observe(&mut self, id: &str, ok: bool, now: SystemTime) -> Option<Transition> {
+        let (window, threshold) = (self.flap_window, self.flap_threshold);
+        let Some(tracked) = self.states.get_mut(id) else {
+            let state = if ok { State::Ok } else { State::Failing };
+            let first_failure = if ok { None } else { Some(now) };
+            self.states.insert(
+                id.to_string(),
+                Tracked {
+                    state,
+                    since: now,
+                    first_failure,
+                    outcomes: VecDeque::from([ok]),
+                },
+            );
+            return Some(Transition {
+                id: id.to_string(),
+                from: None,
+                to: state,
+                at: now,
+                time_in_state: None,
+                first_failure,
+            });
+        };
+
+        tracked.outcomes.push_back(ok);
+        while tracked.outcomes.len() > window {
+            tracked.outcomes.pop_front();
+        }
+        let changes = tracked
+            .outcomes
+            .iter()
+            .zip(tracked.outcomes.iter().skip(1))
+            .filter(|(a, b)| a != b)
+            .count();
+        let state = if changes >= threshold {
+            State::Flapping
+        } else if ok {
+            State::Ok
+        } else {
+            State::Failing
+        };
+        if !ok && tracked.first_failure.is_none() {
+            tracked.first_failure = Some(now);
+        }
+        if state == State::Ok {
+            tracked.first_failure = None;
+        }
+        if state == tracked.state {
+            return None;
+        }
+
+        let transition = Transition {
+            id: id.to_string(),
+            from: Some(tracked.state),
+            to: state,
+            at: now,
+            time_in_state: now.duration_since(tracked.since).ok(),
+            first_failure: tracked.first_failure,
+        };
+        tracked.state = state;
+        tracked.since = now;
+        Some(transition)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::ReporterOutput;
+    use crate::sender::{SenderBuilder, SenderOptions};
+    use mockito::{mock, server_address};
+    use std::sync::{Arc, Mutex};
+
+    type Heard = Arc<Mutex<Vec<(String, String, Option<State>, State)>>>;
+
+    /// Keeps the transitions it hears, with the uri of the iteration.
+    struct Listening(Heard);
+
+    impl ReporterOutput for Listening {
+        fn transition(&mut self, interaction: &Interaction, transition: &Transition) {
+            self.0.lock().unwrap().push((
+                transition.id.clone(),
+                interaction.request.uri.clone(),
+                transition.from,
+                transition.to,
+            ));
+        }
+    }
+
+    #[test]
+    fn test_monitor_ticks_report_transitions_per_iteration() {
+        let _m1 = mock("GET", "/monitor/health").create();
+        let _m2 = mock("GET", "/monitor/a").create();
+        let sequence = SequenceInteractions::from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: health
+    uri: http://{{host}}/monitor/health
+  response:
+    status_code: "200"
+- request:
+    id: pages
+    uri: http://{{host}}/
+    uri_list:
+    - http://{{host}}/monitor/a
+    - http://{{host}}/monitor/b
+  response:
+    status_code: "200"
+"#,
+        )
+        .unwrap();
+        let mut context = Context::new();
+        context
+            .vars_bag
+            .insert("host".to_string(), server_address().to_string());
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let mut monitor = Monitor::new(
+            SequenceRunner::new(sender.as_ref(), false, HashMap::new()),
+            Duration::from_secs(0),
+        );
+        let heard = Heard::default();
+        let mut reporter = Reporter {
+            outputs: vec![Box::new(Listening(Arc::clone(&heard)))],
+        };
+        let mut tick = |status: usize| {
+            let _m = mock("GET", "/monitor/b").with_status(status).create();
+            monitor.tick_into(&context, &sequence, &mut reporter).len()
+        };
+
+        assert_eq!(tick(200), 3);
+        assert_eq!(tick(500), 1);
+        assert_eq!(tick(500), 0);
+        assert_eq!(tick(200), 1);
+
+        assert_eq!(
+            heard
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(id, uri, from, to)| (id.as_str(), uri.as_str(), *from, *to))
+                .collect::<Vec<_>>(),
+            vec![
+                ("health", "http://{{host}}/monitor/health", None, State::Ok),
+                ("pages[0]", "http://{{host}}/monitor/a", None, State::Ok),
+                ("pages[1]", "http://{{host}}/monitor/b", None, State::Ok),
+                (
+                    "pages[1]",
+                    "http://{{host}}/monitor/b",
+                    Some(State::Ok),
+                    State::Failing
+                ),
+                (
+                    "pages[1]",
+                    "http://{{host}}/monitor/b",
+                    Some(State::Failing),
+                    State::Ok
+                ),
+            ]
+        );
+        assert_eq!(monitor.states()["pages[0]"], State::Ok);
+    }
+
+    #[test]
+    fn test_monitor_reports_state_changes_only() {
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let mut monitor = Monitor::new(
+            SequenceRunner::new(sender.as_ref(), false, HashMap::new()),
+            Duration::from_secs(0),
+        )
+        .with_flapping(4, 2);
+        let start = SystemTime::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let first = monitor.observe("login", true, at(0)).unwrap();
+        assert_eq!((first.from, first.to), (None, State::Ok));
+        assert!(monitor.observe("login", true, at(10)).is_none());
+
+        let failing = monitor.observe("login", false, at(20)).unwrap();
+        assert_eq!(
+            (failing.from, failing.to),
+            (Some(State::Ok), State::Failing)
+        );
+        assert_eq!(failing.time_in_state, Some(Duration::from_secs(20)));
+        assert_eq!(failing.first_failure, Some(at(20)));
+        assert!(monitor.observe("login", false, at(30)).is_none());
+
+        let flapping = monitor.observe("login", true, at(40)).unwrap();
+        assert_eq!(flapping.to, State::Flapping);
+        assert_eq!(flapping.first_failure, Some(at(20)));
+
+        let ok = monitor.observe("login", true, at(50)).unwrap();
+        assert_eq!((ok.from, ok.to), (Some(State::Flapping), State::Ok));
+        assert_eq!(ok.first_failure, None);
+        assert_eq!(monitor.states()["login"], State::Ok);
+    }
+}
@@ -0,0 +1,370 @@
+use crate::data::{CheckResult, Context, Interaction, ResponseBag, SequenceInteractions, VarsBag};
+use crate::graph::{data_dependencies, dependencies};
+use crate::reporters::Reporter;
+use crate::runner::{RunOptions, RunnerReport, SequenceRunner, Settings};
+use std::collections::VecDeque;
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Vars and responses a single interaction added to its copy of the context.
+struct Outcome {
+    idx: usize,
+    results: Vec<CheckResult>,
+    vars: VarsBag,
+    responses: ResponseBag,
+}
+
+/// Marks `idx` as done, queueing the interactions that were only waiting on it.
+fn release(deps: &[Vec<usize>], waiting: &mut [usize], ready: &mut VecDeque<usize>, idx: usize) {
+    for (i, d) in deps.iter().enumerate() {
+        if d.contains(&idx) {
+            waiting[i] -= 1;
+            if waiting[i] == 0 {
+                ready.push_back(i);
+            }
+        }
+    }
+}
+
+/// Runs independent interactions of a sequence at the same time.
+///
+/// Dependencies are inferred with `graph::dependencies`: an interaction starts
+/// only once every interaction producing the vars it uses (or listed in its
+/// `depends_on`) has finished. Results are reported as they complete, and
+/// returned in sequence order. With `SequenceRunner::with_checkpoint`, a
+/// checkpoint is saved as each interaction completes, in completion order.
+#[allow(clippy::module_name_repetitions)]
+pub struct ParallelRunner<'a> {
+    runner: SequenceRunner<'a>,
+    workers: usize,
+}
+
+impl<'a> ParallelRunner<'a> {
+    #[must_use]
+    pub fn new(runner: SequenceRunner<'a>, workers: usize) -> Self {
+        Self {
+            runner,
+            workers: workers.max(1),
+        }
+    }
+
+    #[must_use]
+    pub fn from_opts(run_opts: &'a RunOptions) -> Self {
+        Self::new(SequenceRunner::from_opts(run_opts), run_opts.workers)
+    }
+
+    pub fn run(&self, context: &mut Context, sequence: &[Interaction]) -> RunnerReport {
+        self.runner.journal().start(&[]);
+        let mut reporter = self.runner.settings().start_reporter(sequence);
+        let results = self.execute(context, sequence, &mut reporter);
+        reporter.end(sequence, &results);
+        let ok = self.runner.settings().is_ok(&results);
+        RunnerReport::new(ok, results)
+    }
+
+    /// Like `SequenceRunner::run_sequence`, with setup and teardown running
+    /// one after the other around the parallel main interactions.
+    pub fn run_sequence(
+        &self,
+        context: &mut Context,
+        sequence: &SequenceInteractions,
+    ) -> RunnerReport {
+        self.runner.journal().start(&[]);
+        let mut reporter = self
+            .runner
+            .settings()
+            .start_reporter(&sequence.all_interactions());
+        let results = self
+            .runner
+            .run_phases(context, sequence, &mut reporter, |ctx, reporter| {
+                self.execute(ctx, &sequence.http_interactions, reporter)
+            });
+        reporter.end(&sequence.all_interactions(), &results);
+        let ok = self.runner.settings().is_ok(&results);
+        RunnerReport::new(ok, results)
+    }
+
+    fn execute(
+        &self,
+        context: &mut Context,
+        sequence: &[Interaction],
+        reporter: &mut Reporter<'_>,
+    ) -> Vec<CheckResult> {
+        let deps = dependencies(sequence, &context.var_format());
+        // only a failure the interaction takes data from skips it
+        let data_deps = data_dependencies(sequence, &context.var_format());
+        let mut waiting = deps.iter().map(Vec::len).collect::<Vec<_>>();
+        let mut ready = (0..sequence.len())
+            .filter(|i| waiting[*i] == 0)
+            .collect::<VecDeque<_>>();
+        let mut collected: Vec<Option<Vec<CheckResult>>> =
+            (0..sequence.len()).map(|_| None).collect();
+
+        thread::scope(|scope| {
+            let (job_tx, job_rx) = channel::<(usize, Context)>();
+            let (done_tx, done_rx) = channel::<Outcome>();
+            let job_rx = Arc::new(Mutex::new(job_rx));
+
+            for _ in 0..self.workers.min(sequence.len()) {
+                let job_rx = Arc::clone(&job_rx);
+                let done_tx = done_tx.clone();
+                scope.spawn(move || loop {
+                    let job = job_rx.lock().unwrap().recv();
+                    let Ok((idx, mut ctx)) = job else {
+                        break;
+                    };
+                    let before = ctx.clone();
+                    let results = self.runner.perform(&mut ctx, &sequence[idx], None);
+                    let outcome = Outcome {
+                        idx,
+                        results,
+                        vars: ctx
+                            .vars_bag
+                            .into_iter()
+                            .filter(|(k, v)| before.vars_bag.get(k) != Some(v))
+                            .collect(),
+                        responses: ctx
+                            .response_bag
+                            .into_iter()
+                            .filter(|(k, v)| before.response_bag.get(k) != Some(v))
+                            .collect(),
+                    };
+                    if done_tx.send(outcome).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(done_tx);
+
+            let mut in_flight = 0;
+            let mut failed = vec![];
+            let mut aborted = false;
+            loop {
+                while let Some(idx) = ready.pop_front() {
+                    if aborted {
+                        continue;
+                    }
+                    if let Some(reason) =
+                        self.runner
+                            .settings()
+                            .skip_reason(sequence, &data_deps[idx], &failed)
+                    {
+                        failed.push(idx);
+                        collected[idx] =
+                            Some(Settings::skip(&sequence[idx], &reason, Some(reporter)));
+                        release(&deps, &mut waiting, &mut ready, idx);
+                        continue;
+                    }
+                    if let Some(results) =
+                        self.runner
+                            .settings()
+                            .gate(context, &sequence[idx], Some(reporter))
+                    {
+                        self.runner
+                            .journal()
+                            .record(context, &sequence[idx], &results);
+                        if !self.runner.settings().is_ok(&results) {
+                            failed.push(idx);
+                            aborted = self.runner.settings().exit_on_failure();
+                        }
+                        collected[idx] = Some(results);
+                        release(&deps, &mut waiting, &mut ready, idx);
+                        continue;
+                    }
+                    job_tx.send((idx, context.clone())).unwrap();
+                    in_flight += 1;
+                }
+                if in_flight == 0 {
+                    break;
+                }
+                let outcome = done_rx.recv().unwrap();
+                in_flight -= 1;
+
+                context.vars_bag.extend(outcome.vars);
+                context.response_bag.extend(outcome.responses);
+                let interaction = &sequence[outcome.idx];
+                self.runner
+                    .journal()
+                    .record(context, interaction, &outcome.results);
+                for res in &outcome.results {
+                    // iterations of a matrix or uri_list carry their own request id
+                    let mut shown = interaction.clone();
+                    shown.request = res.request.clone();
+                    reporter.start(&shown);
+                    reporter.report(&shown, res);
+                }
+                if !self.runner.settings().is_ok(&outcome.results) {
+                    failed.push(outcome.idx);
+                    aborted = self.runner.settings().exit_on_failure();
+                }
+                collected[outcome.idx] = Some(outcome.results);
+                release(&deps, &mut waiting, &mut ready, outcome.idx);
+            }
+            drop(job_tx);
+        });
+
+        collected.into_iter().flatten().flatten().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::Checkpoint;
+    use crate::data::{CheckStatus, Runner};
+    use crate::sender::{SenderBuilder, SenderOptions};
+    use mockito::{mock, server_address};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parallel_runner_passes_vars_along_dependencies() {
+        let _m1 = mock("GET", "/login").with_body("token: t0k3n").create();
+        let _m2 = mock("GET", "/health").with_body("up").create();
+        let _m3 = mock("GET", "/me")
+            .match_header("authorization", "Bearer t0k3n")
+            .with_body("joe")
+            .create();
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: login
+    uri: http://{{host}}/login
+    vars:
+      token:
+        from: /body
+        expr: "token: (.*)"
+  response:
+    status_code: "200"
+- request:
+    id: health
+    uri: http://{{host}}/health
+  response:
+    body: up
+- request:
+    id: profile
+    uri: http://{{host}}/me
+    headers:
+      Authorization:
+      - Bearer {{token}}
+  response:
+    body: joe
+"#,
+        )
+        .unwrap();
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+
+        let path = std::env::temp_dir().join(format!(
+            "spk-parallel-checkpoint-{}.json",
+            std::process::id()
+        ));
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let runner = ParallelRunner::new(
+            SequenceRunner::new(sender.as_ref(), false, HashMap::new()).with_checkpoint(&path),
+            3,
+        );
+        let report = runner.run(&mut ctx, &interactions);
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(report.ok);
+        assert_eq!(
+            report
+                .results
+                .iter()
+                .map(|r| r.request.get_id())
+                .collect::<Vec<_>>(),
+            vec!["login", "health", "profile"]
+        );
+        assert_eq!(ctx.vars_bag.get("token").unwrap(), "t0k3n");
+        assert_eq!(checkpoint.results.len(), 3);
+        assert_eq!(
+            checkpoint.context.vars_bag.get("token").map(String::as_str),
+            Some("t0k3n")
+        );
+    }
+
+    #[test]
+    fn test_parallel_runner_skips_only_dependents_taking_data_from_failures() {
+        let _m1 = mock("GET", "/a").with_body("token: ta").create();
+        let _m2 = mock("GET", "/me_a").with_status(500).create();
+        let _m3 = mock("GET", "/b").with_body("token: tb").create();
+        let _m4 = mock("GET", "/me_b")
+            .match_header("authorization", "Bearer tb")
+            .with_body("joe")
+            .create();
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: login_a
+    uri: http://{{host}}/a
+    vars:
+      token:
+        from: /body
+        expr: "token: (.*)"
+  response:
+    status_code: "200"
+- request:
+    id: use_a
+    uri: http://{{host}}/me_a
+    headers:
+      Authorization:
+      - Bearer {{token}}
+  response:
+    status_code: "200"
+- request:
+    id: login_b
+    uri: http://{{host}}/b
+    vars:
+      token:
+        from: /body
+        expr: "token: (.*)"
+  response:
+    status_code: "200"
+- request:
+    id: use_b
+    uri: http://{{host}}/me_b
+    headers:
+      Authorization:
+      - Bearer {{token}}
+  response:
+    body: joe
+"#,
+        )
+        .unwrap();
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let runner = ParallelRunner::new(
+            SequenceRunner::new(sender.as_ref(), false, HashMap::new()).with_runner(Runner {
+                skip_dependents: true,
+                ..Runner::default()
+            }),
+            4,
+        );
+        let report = runner.run(&mut ctx, &interactions);
+
+        // login_b waits for use_a to keep `token` in order, but takes nothing
+        // from it
+        let found = report
+            .results
+            .iter()
+            .map(|r| (r.request.get_id(), r.status()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                ("login_a".to_string(), CheckStatus::Passed),
+                ("use_a".to_string(), CheckStatus::Failed),
+                ("login_b".to_string(), CheckStatus::Passed),
+                ("use_b".to_string(), CheckStatus::Passed),
+            ]
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use crate::data::{AsyncSender, Interaction, Permit, Response, Sender};
+use anyhow::Result as AnyResult;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Run-wide limits on how requests go out, see `RunOptions::with_rate_limit`.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    /// At most this many requests start per second.
+    pub requests_per_second: Option<f64>,
+    /// At most this many requests in flight to the same host and port.
+    pub per_host_concurrency: Option<usize>,
+    /// At least this long between the start of two requests.
+    pub min_spacing_ms: Option<u64>,
+}
+
+impl RateLimit {
+    /// Time between request starts that satisfies both the rate and spacing.
+    #[must_use]
+    pub fn interval(&self) -> Duration {
+        let rate = self
+            .requests_per_second
+            .filter(|rps| *rps > 0.0)
+            .map_or(Duration::ZERO, |rps| Duration::from_secs_f64(1.0 / rps));
+        let spacing = Duration::from_millis(self.min_spacing_ms.unwrap_or_default());
+        rate.max(spacing)
+    }
+}
+
+// host and port a request goes to, or the raw uri when it does not parse
+fn host_of(uri: &str) -> String {
+    reqwest::Url::parse(uri).map_or_else(
+        |_| uri.to_string(),
+        |url| {
+            format!(
+                "{}:{}",
+                url.host_str().unwrap_or_default(),
+                url.port_or_known_default().unwrap_or_default()
+            )
+        },
+    )
+}
+
+/// Holds a slot of a host's concurrency until dropped.
+struct HostPermit<'s> {
+    limiter: &'s Limiter,
+    host: String,
+}
+
+impl<'s> Drop for HostPermit<'s> {
+    fn drop(&mut self) {
+        let mut hosts = self.limiter.hosts.lock().unwrap();
+        if let Some(count) = hosts.get_mut(&self.host) {
+            *count -= 1;
+        }
+        self.limiter.released.notify_all();
+        self.limiter.freed.notify_waiters();
+    }
+}
+
+// the limits' state, waited on by blocking and async senders alike
+struct Limiter {
+    limit: RateLimit,
+    next_start: Mutex<Option<Instant>>,
+    hosts: Mutex<HashMap<String, usize>>,
+    released: Condvar,
+    freed: Notify,
+}
+
+impl Limiter {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            next_start: Mutex::new(None),
+            hosts: Mutex::new(HashMap::new()),
+            released: Condvar::new(),
+            freed: Notify::new(),
+        }
+    }
+
+    // takes a slot of the host's concurrency when one is free
+    fn try_host(&self, host: &str, cap: usize) -> Option<HostPermit<'_>> {
+        let mut hosts = self.hosts.lock().unwrap();
+        let count = hosts.entry(host.to_string()).or_default();
+        (*count < cap).then(|| {
+            *count += 1;
+            HostPermit {
+                limiter: self,
+                host: host.to_string(),
+            }
+        })
+    }
+
+    fn acquire_host(&self, uri: &str) -> Option<HostPermit<'_>> {
+        let cap = self.limit.per_host_concurrency?.max(1);
+        let host = host_of(uri);
+        let mut hosts = self.hosts.lock().unwrap();
+        while hosts.get(&host).copied().unwrap_or_default() >= cap {
+            hosts = self.released.wait(hosts).unwrap();
+        }
+        *hosts.entry(host.clone()).or_default() += 1;
+        Some(HostPermit {
+            limiter: self,
+            host,
+        })
+    }
+
+    async fn acquire_host_async(&self, uri: &str) -> Option<HostPermit<'_>> {
+        let cap = self.limit.per_host_concurrency?.max(1);
+        let host = host_of(uri);
+        loop {
+            // listen before looking, so a release in between is not missed
+            let freed = self.freed.notified();
+            tokio::pin!(freed);
+            freed.as_mut().enable();
+            if let Some(permit) = self.try_host(&host, cap) {
+                return Some(permit);
+            }
+            freed.await;
+        }
+    }
+
+    // reserves the next start slot, returning how long to wait for it
+    fn reserve_start(&self) -> Duration {
+        let interval = self.limit.interval();
+        if interval.is_zero() {
+            return Duration::ZERO;
+        }
+        let start = {
+            let mut next_start = self.next_start.lock().unwrap();
+            let now = Instant::now();
+            let start = next_start.map_or(now, |next| next.max(now));
+            *next_start = Some(start + interval);
+            start
+        };
+        start.saturating_duration_since(Instant::now())
+    }
+
+    fn pace(&self) {
+        sleep(self.reserve_start());
+    }
+
+    async fn pace_async(&self) {
+        let wait = self.reserve_start();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Sends through another `Sender` within a `RateLimit`. Every check and
+/// runner sending through it, parallel ones included, shares the limits.
+#[allow(clippy::module_name_repetitions)]
+pub struct RateLimitedSender {
+    inner: Box<dyn Sender>,
+    limiter: Limiter,
+}
+
+impl RateLimitedSender {
+    #[must_use]
+    pub fn new(inner: Box<dyn Sender>, limit: RateLimit) -> Self {
+        Self {
+            inner,
+            limiter: Limiter::new(limit),
+        }
+    }
+}
+
+impl Sender for RateLimitedSender {
+    fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+        let _permit = self.limiter.acquire_host(&inter.request.uri);
+        self.limiter.pace();
+        self.inner.send(inter)
+    }
+
+    fn permit(&self, uri: &str) -> AnyResult<Permit<'_>> {
+        let host = self.limiter.acquire_host(uri);
+        self.limiter.pace();
+        Ok(Box::new((host, self.inner.permit(uri)?)))
+    }
+}
+
+/// `RateLimitedSender` for an `AsyncSender`, waiting without blocking the
+/// runtime. Concurrent runs sharing it share the limits.
+#[allow(clippy::module_name_repetitions)]
+pub struct AsyncRateLimitedSender {
+    inner: Box<dyn AsyncSender>,
+    limiter: Limiter,
+}
+
+impl AsyncRateLimitedSender {
+    #[must_use]
+    pub fn new(inner: Box<dyn AsyncSender>, limit: RateLimit) -> Self {
+        Self {
+            inner,
+            limiter: Limiter::new(limit),
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncSender for AsyncRateLimitedSender {
+    async fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+        let _permit = self.limiter.acquire_host_async(&inter.request.uri).await;
+        self.limiter.pace_async().await;
+        self.inner.send(inter).await
+    }
+
+    async fn permit<'s>(&'s self, uri: &str) -> AnyResult<Permit<'s>> {
+        let host = self.limiter.acquire_host_async(uri).await;
+        self.limiter.pace_async().await;
+        Ok(Box::new((host, self.inner.permit(uri).await?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Takes a while to respond, tracking how many requests overlap.
+    struct SlowSender {
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl Sender for SlowSender {
+        fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+            sleep(Duration::from_millis(20));
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(Response {
+                request_id: Some(inter.request.get_id()),
+                headers: None,
+                status_code: Some("200".to_string()),
+                body: None,
+                vars: None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_rate_limited_sender_spaces_and_caps_requests() {
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let sender = RateLimitedSender::new(
+            Box::new(SlowSender {
+                in_flight: Arc::new(AtomicUsize::new(0)),
+                max_in_flight: Arc::clone(&max_in_flight),
+            }),
+            RateLimit {
+                requests_per_second: Some(100.0),
+                per_host_concurrency: Some(1),
+                min_spacing_ms: Some(15),
+            },
+        );
+        assert_eq!(sender.limiter.limit.interval(), Duration::from_millis(15));
+        let inter = Interaction::from_yaml("request:\n  uri: https://example.com/a\n").unwrap();
+
+        let started = Instant::now();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| sender.send(&inter).unwrap());
+            }
+        });
+
+        assert!(started.elapsed() >= Duration::from_millis(80));
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 1);
+        assert_eq!(host_of("https://example.com/a"), "example.com:443");
+
+        // connections made outside of `send` keep the same spacing
+        let started = Instant::now();
+        for _ in 0..2 {
+            drop(sender.permit("https://example.com/a").unwrap());
+        }
+        assert!(started.elapsed() >= Duration::from_millis(15));
+    }
+
+    /// `SlowSender` for async runs.
+    struct SlowAsyncSender {
+        in_flight: AtomicUsize,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AsyncSender for SlowAsyncSender {
+        async fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(Response {
+                request_id: Some(inter.request.get_id()),
+                headers: None,
+                status_code: Some("200".to_string()),
+                body: None,
+                vars: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_rate_limited_sender_spaces_and_caps_requests() {
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let sender = AsyncRateLimitedSender::new(
+            Box::new(SlowAsyncSender {
+                in_flight: AtomicUsize::new(0),
+                max_in_flight: Arc::clone(&max_in_flight),
+            }),
+            RateLimit {
+                requests_per_second: None,
+                per_host_concurrency: Some(1),
+                min_spacing_ms: Some(15),
+            },
+        );
+        let inter = Interaction::from_yaml("request:\n  uri: https://example.com/a\n").unwrap();
+
+        let started = Instant::now();
+        let (a, b, c, d) = tokio::join!(
+            sender.send(&inter),
+            sender.send(&inter),
+            sender.send(&inter),
+            sender.send(&inter)
+        );
+
+        assert!(a.and(b).and(c).and(d).is_ok());
+        assert!(started.elapsed() >= Duration::from_millis(80));
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 1);
+
+        let started = Instant::now();
+        for _ in 0..2 {
+            drop(sender.permit("https://example.com/a").await.unwrap());
+        }
+        assert!(started.elapsed() >= Duration::from_millis(15));
+    }
+}
@@ -0,0 +1,79 @@
+use crate::auth_enforcement::{self, AuthEnforcement};
+use crate::cookies::{self, Cookies};
+use crate::cors::{self, Cors};
+use crate::data::{Check, Sender};
+use crate::https_redirect::{self, HttpsRedirect};
+use crate::security_headers::{self, SecurityHeaders};
+#[cfg(feature = "tls")]
+use crate::tls::{self, Tls};
+use anyhow::Result as AnyResult;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Config of a built-in check, read from its entry under `checks:`.
+pub trait CheckConfig: DeserializeOwned {
+    /// Every field takes its default when the check is listed without config.
+    fn from_config(config: Option<&serde_json::Value>) -> AnyResult<Self> {
+        let config = config
+            .filter(|config| !config.is_null())
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({}));
+        Ok(serde_json::from_value(config)?)
+    }
+}
+
+pub type CheckFactory = dyn for<'a> Fn(&'a dyn Sender) -> Box<dyn Check + 'a> + Send + Sync;
+
+/// Maps a check name, as used under an interaction's `checks:` section, to a
+/// factory producing the `Check` that handles it.
+#[derive(Clone, Default)]
+#[allow(clippy::module_name_repetitions)]
+pub struct CheckRegistry {
+    factories: BTreeMap<String, Arc<CheckFactory>>,
+}
+
+impl CheckRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<F>(&mut self, name: &str, factory: F) -> &mut Self
+    where
+        F: for<'a> Fn(&'a dyn Sender) -> Box<dyn Check + 'a> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Arc::new(factory));
+        self
+    }
+
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    #[must_use]
+    pub fn names(&self) -> Vec<&str> {
+        self.factories.keys().map(String::as_str).collect()
+    }
+
+    #[must_use]
+    pub fn create<'a>(&self, name: &str, sender: &'a dyn Sender) -> Option<Box<dyn Check + 'a>> {
+        self.factories.get(name).map(|factory| factory(sender))
+    }
+}
+
+/// Checks that ship with the kit. They are used under `checks:` like
+/// registered ones, unless a check of the same name is registered.
+pub(crate) fn builtin<'a>(name: &str, sender: &'a dyn Sender) -> Option<Box<dyn Check + 'a>> {
+    match name {
+        auth_enforcement::NAME => Some(Box::new(AuthEnforcement::new(sender))),
+        cookies::NAME => Some(Box::new(Cookies::new(sender))),
+        cors::NAME => Some(Box::new(Cors::new(sender))),
+        https_redirect::NAME => Some(Box::new(HttpsRedirect::new(sender))),
+        security_headers::NAME => Some(Box::new(SecurityHeaders::new(sender))),
+        #[cfg(feature = "tls")]
+        tls::NAME => Some(Box::new(Tls::new(sender))),
+        _ => None,
+    }
+}
@@ -1,4 +1,6 @@
-use crate::data::{CheckResult, Interaction, ReporterOutput};
+use crate::data::{CheckResult, Expect, Interaction, ReporterOutput, State, Summary, Transition};
+
+use chrono::{DateTime, SecondsFormat, Utc};
 
 use console::style;
 use console::Term;
@@ -7,6 +9,8 @@ use std::collections::HashMap;
 use std::fmt::Write;
 const FAIL_SIGN: &str = "✗";
 const SUCCESS_SIGN: &str = "✔";
+const SKIP_SIGN: &str = "↷";
+const FLAP_SIGN: &str = "~";
 pub struct ConsoleOutput {
     buffer: String,
     verbose: bool,
@@ -130,7 +134,44 @@ impl ReporterOutput for ConsoleOutput {
     }
     fn report(&mut self, interaction: &Interaction, check_results: &CheckResult) {
         self.buffer.clear();
-        if !check_results.violations.is_empty() {
+        if let Some(reason) = &check_results.skipped {
+            writeln!(
+                self.buffer,
+                "{} {}: {} {}",
+                style(SKIP_SIGN).dim(),
+                interaction.request.get_id(),
+                style("skipped").dim(),
+                style(reason).dim(),
+            )
+            .unwrap();
+        } else if let Some(expect) = check_results.expect.filter(|e| *e != Expect::Pass) {
+            let outcome = check_results.outcome();
+            let (sign, label) = if outcome == expect {
+                let done = match outcome {
+                    Expect::Pass => "passed",
+                    Expect::Violation => "failed",
+                    Expect::Error => "errored",
+                };
+                (
+                    style(SUCCESS_SIGN).green(),
+                    style(format!("{done} as expected")).green(),
+                )
+            } else {
+                (
+                    style(FAIL_SIGN).red(),
+                    style(format!("expected {expect:?}, got {outcome:?}").to_lowercase()).red(),
+                )
+            };
+            writeln!(
+                self.buffer,
+                "{} {}: {} {}",
+                sign,
+                interaction.request.get_id(),
+                label,
+                style(format!("{}ms", check_results.duration.unwrap().as_millis())).dim(),
+            )
+            .unwrap();
+        } else if !check_results.violations.is_empty() {
             writeln!(
                 self.buffer,
                 "{} {}: {} {}",
@@ -174,26 +215,68 @@ impl ReporterOutput for ConsoleOutput {
             )
             .unwrap();
         }
+        if self.verbose {
+            for (i, hop) in check_results.hops.iter().flatten().enumerate() {
+                writeln!(
+                    self.buffer,
+                    "      hop {}: {} {} {}",
+                    i + 1,
+                    hop.status,
+                    hop.uri,
+                    style(hop.location.as_deref().unwrap_or_default()).dim()
+                )
+                .unwrap();
+            }
+        }
 
         Self::overwrite_previous_term();
         self.buffer_to_term();
     }
 
+    fn transition(&mut self, _interaction: &Interaction, transition: &Transition) {
+        self.buffer.clear();
+        let (sign, to) = match transition.to {
+            State::Ok => (style(SUCCESS_SIGN).green(), style("ok").green()),
+            State::Failing => (style(FAIL_SIGN).red(), style("failing").red()),
+            State::Flapping => (style(FLAP_SIGN).yellow(), style("flapping").yellow()),
+        };
+        write!(self.buffer, "{} {}: {}", sign, transition.id, to).unwrap();
+        if let (Some(from), Some(time)) = (transition.from, transition.time_in_state) {
+            let from = format!("{from:?}").to_lowercase();
+            write!(
+                self.buffer,
+                " {}",
+                style(format!("after {}s {from}", time.as_secs())).dim()
+            )
+            .unwrap();
+        }
+        if let Some(first_failure) = transition.first_failure {
+            let since =
+                DateTime::<Utc>::from(first_failure).to_rfc3339_opts(SecondsFormat::Secs, true);
+            write!(
+                self.buffer,
+                " {}",
+                style(format!("failing since {since}")).dim()
+            )
+            .unwrap();
+        }
+        writeln!(self.buffer).unwrap();
+
+        self.buffer_to_term();
+    }
+
     fn end(&mut self, interactions: &[Interaction], results: &[CheckResult]) {
         self.buffer.clear();
         if interactions.is_empty() {
             writeln!(self.buffer, "No interactions found").unwrap();
         } else {
-            let duration = results
-                .iter()
-                .filter_map(|c| c.duration)
-                .reduce(|d, acc| d + acc);
+            let summary = Summary::from_results(results);
             write!(
                 self.buffer,
                 "\nRan {} interactions with {} checks in {}\n",
                 style(interactions.len()).yellow(),
                 style(results.len()).yellow(),
-                style(format!("{}ms", duration.unwrap().as_millis())).yellow(),
+                style(format!("{}ms", summary.duration.as_millis())).yellow(),
             )
             .unwrap();
 
@@ -201,32 +284,10 @@ impl ReporterOutput for ConsoleOutput {
                 write!(
                     self.buffer,
                     "\nSuccess: {}\nFailure: {}\n  Error: {}\nSkipped: {}\n",
-                    style(
-                        results
-                            .iter()
-                            .filter(|c| c.error.is_none()
-                                && c.response.is_some()
-                                && c.violations.is_empty())
-                            .count()
-                    )
-                    .green(),
-                    style(
-                        results
-                            .iter()
-                            .filter(|c| c.error.is_none()
-                                && c.response.is_some()
-                                && !c.violations.is_empty())
-                            .count()
-                    )
-                    .red(),
-                    style(results.iter().filter(|c| c.error.is_some()).count()).red(),
-                    style(
-                        results
-                            .iter()
-                            .filter(|c| c.error.is_none() && c.response.is_none())
-                            .count()
-                    )
-                    .dim(),
+                    style(summary.counts.passed).green(),
+                    style(summary.counts.failed).red(),
+                    style(summary.counts.errored).red(),
+                    style(summary.counts.skipped).dim(),
                 )
                 .unwrap();
             }
@@ -273,13 +334,11 @@ mod tests {
             o.buffer.to_string(),
             "• postbin:validation: \u{1b}[35mstarted\u{1b}[0m\n"
         );
-        let fake_result = CheckResult {
-            kind: "content".to_string(),
-            request: inter.request.clone(),
-            response: None,
-            duration: Some(Duration::new(2, 0)),
-            error: None,
-            violations: vec![Violation {
+        let fake_result = CheckResult::new(
+            "content",
+            inter.request.clone(),
+            None,
+            vec![Violation {
                 kind: "content".to_string(),
                 cause: Cause::WireMissing,
                 subject: "content".to_string(),
@@ -287,14 +346,15 @@ mod tests {
                 wire: None,
                 recorded: String::new(),
             }],
-        };
+            Duration::new(2, 0),
+        );
         o.report(&inter, &fake_result);
         assert_eq!(
             o.buffer.to_string(),
             "\u{1b}[31m✗\u{1b}[0m postbin:validation: \u{1b}[31mfailed\u{1b}[0m \u{1b}[2m2000ms\u{1b}[0m\n      content: \n"
         );
 
-        o.end(&vec![inter], &vec![fake_result]);
-        assert_eq!(o.buffer.to_string(), "\nRan \u{1b}[33m1\u{1b}[0m interactions with \u{1b}[33m1\u{1b}[0m checks in \u{1b}[33m2000ms\u{1b}[0m\n\nSuccess: \u{1b}[32m0\u{1b}[0m\nFailure: \u{1b}[31m0\u{1b}[0m\n  Error: \u{1b}[31m0\u{1b}[0m\nSkipped: \u{1b}[2m1\u{1b}[0m\n");
+        o.end(&[inter], &[fake_result]);
+        assert_eq!(o.buffer.to_string(), "\nRan \u{1b}[33m1\u{1b}[0m interactions with \u{1b}[33m1\u{1b}[0m checks in \u{1b}[33m2000ms\u{1b}[0m\n\nSuccess: \u{1b}[32m0\u{1b}[0m\nFailure: \u{1b}[31m1\u{1b}[0m\n  Error: \u{1b}[31m0\u{1b}[0m\nSkipped: \u{1b}[2m0\u{1b}[0m\n");
     }
 }
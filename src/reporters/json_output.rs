@@ -1,4 +1,4 @@
-use crate::data::{CheckResult, Interaction, ReporterConfig, ReporterOutput};
+use crate::data::{CheckResult, Interaction, ReporterConfig, ReporterOutput, Summary, Transition};
 use serde::Serialize;
 use serde_json;
 
@@ -6,6 +6,7 @@ use serde_json;
 pub struct EndEvent<'a> {
     interactions: &'a [Interaction],
     results: &'a [CheckResult],
+    summary: Summary,
 }
 pub struct JsonOutput {}
 unsafe impl Sync for JsonOutput {}
@@ -22,8 +23,12 @@ impl ReporterOutput for JsonOutput {
             serde_json::to_value(EndEvent {
                 interactions,
                 results,
+                summary: Summary::from_results(results),
             })
             .unwrap()
         );
     }
+    fn transition(&mut self, _interaction: &Interaction, transition: &Transition) {
+        println!("{}", serde_json::to_value(transition).unwrap());
+    }
 }
@@ -1,4 +1,4 @@
-use crate::data::{CheckResult, Interaction, ReporterConfig, ReporterOutput};
+use crate::data::{CheckResult, CheckStatus, Expect, Interaction, ReporterConfig, ReporterOutput};
 use junit_report::{Duration as JUnitDuration, Report, TestCase, TestSuite};
 use std::fs;
 pub struct JUnitOutput {
@@ -18,9 +18,11 @@ impl ReporterOutput for JUnitOutput {
         let mut suite = TestSuite::new("Violation Checks");
         let mut cases = vec![];
         for res in results {
-            let success = res.violations.is_empty();
+            let success = res.status() == CheckStatus::Passed;
             let test_name = format!("[{}] {}", res.kind, res.request.get_id());
-            if success {
+            if res.status() == CheckStatus::Skipped {
+                cases.push(TestCase::skipped(test_name.as_str()));
+            } else if success {
                 cases.push(TestCase::success(
                     test_name.as_str(),
                     JUnitDuration::from_std(res.duration.unwrap()).unwrap(),
@@ -30,7 +32,16 @@ impl ReporterOutput for JUnitOutput {
                     test_name.as_str(),
                     JUnitDuration::from_std(res.duration.unwrap()).unwrap(),
                     "ERROR",
-                    serde_yaml::to_string(&res.violations).unwrap().as_str(),
+                    &if res.violations.is_empty() {
+                        format!(
+                            "expected {:?}, got {:?}",
+                            res.expect.unwrap_or(Expect::Pass),
+                            res.outcome()
+                        )
+                        .to_lowercase()
+                    } else {
+                        serde_yaml::to_string(&res.violations).unwrap()
+                    },
                 ));
             }
         }
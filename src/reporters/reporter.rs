@@ -1,7 +1,7 @@
 pub use super::console_output::ConsoleOutput;
 pub use super::json_output::JsonOutput;
 pub use super::junit_output::JUnitOutput;
-use crate::data::{CheckResult, Interaction, ReporterConfig, ReporterOutput};
+use crate::data::{CheckResult, Interaction, ReporterConfig, ReporterOutput, Transition};
 use std::collections::HashMap;
 use std::marker::Sync;
 pub struct Reporter<'a> {
@@ -24,6 +24,10 @@ impl<'a> Reporter<'a> {
                 .collect(),
         }
     }
+    pub fn begin(&mut self, interactions: &[Interaction]) {
+        self.outputs.iter_mut().for_each(|r| r.begin(interactions));
+    }
+
     pub fn start(&mut self, inter: &Interaction) {
         self.outputs.iter_mut().for_each(|r| r.start(inter));
     }
@@ -34,6 +38,12 @@ impl<'a> Reporter<'a> {
             .for_each(|r| r.report(inter, check_result));
     }
 
+    pub fn transition(&mut self, inter: &Interaction, transition: &Transition) {
+        self.outputs
+            .iter_mut()
+            .for_each(|r| r.transition(inter, transition));
+    }
+
     pub fn end(&mut self, interactions: &[Interaction], results: &[CheckResult]) {
         self.outputs
             .iter_mut()
@@ -1,14 +1,35 @@
-use crate::reporters::create_reporter;
+use crate::reporters::{create_reporter, Reporter};
+use anyhow::Result as AnyResult;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::thread::sleep;
 
-use crate::content::ContentCheck;
-use crate::data::{Check, CheckResult, Context, Interaction, ReporterConfig, Sender};
+use crate::bench::{self, Bench};
+use crate::cert::{self, Cert};
+use crate::checkpoint::{self, Checkpoint, Journal};
+use crate::condition;
+use crate::content::{self, ContentCheck};
+use crate::data::{
+    Check, CheckResult, Context, Expect, Interaction, ReporterConfig, Retry, Runner, Sender,
+    SequenceInteractions, Summary, VarsBag,
+};
+use crate::events::{EventOutput, RunEvents};
+use crate::graph::data_dependencies;
+use crate::matcher::RegexMatcher;
+use crate::matrix;
+use crate::rate_limit::{RateLimit, RateLimitedSender};
+use crate::registry::{self, CheckRegistry};
+use crate::selection::Selection;
 use crate::sender::{SenderBuilder, SenderOptions};
 
 pub struct RunOptions {
     pub sender: Box<dyn Sender>,
     pub flip: bool,
     pub reporters: HashMap<String, ReporterConfig>,
+    pub registry: CheckRegistry,
+    pub workers: usize,
+    pub runner: Runner,
+    pub selection: Selection,
 }
 impl Default for RunOptions {
     fn default() -> Self {
@@ -39,15 +60,237 @@ impl RunOptions {
             sender,
             flip,
             reporters,
+            registry: CheckRegistry::new(),
+            workers: 4,
+            runner: Runner::default(),
+            selection: Selection::default(),
         }
     }
+
+    /// Sends every request of the run within `limit`, for checks and
+    /// runners alike.
+    #[must_use]
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.sender = Box::new(RateLimitedSender::new(self.sender, limit));
+        self
+    }
+}
+
+/// How a run is judged, reported and cut short, independent of the sender
+/// performing its requests. Shared by the blocking and async runners.
+#[derive(Clone)]
+pub(crate) struct Settings {
+    pub(crate) flip: bool,
+    pub(crate) reporters: HashMap<String, ReporterConfig>,
+    pub(crate) runner: Runner,
+    pub(crate) selection: Selection,
+    pub(crate) events: Option<RunEvents>,
+}
+
+impl Settings {
+    pub(crate) const fn exit_on_failure(&self) -> bool {
+        self.runner.exit_on_failure
+    }
+
+    /// Creates the configured reporters, plus an event stream when one is
+    /// set, and tells them which interactions are about to run.
+    pub(crate) fn start_reporter<'r>(&self, interactions: &[Interaction]) -> Reporter<'r> {
+        let mut reporter = create_reporter(&self.reporters);
+        if let Some(events) = &self.events {
+            reporter
+                .outputs
+                .push(Box::new(EventOutput::new(events.clone())));
+        }
+        reporter.begin(interactions);
+        reporter
+    }
+
+    /// Concludes every check of an interaction without performing it.
+    pub(crate) fn conclude(
+        interaction: &Interaction,
+        mut reporter: Option<&mut Reporter<'_>>,
+        result: impl Fn(&str) -> CheckResult,
+    ) -> Vec<CheckResult> {
+        check_kinds(interaction)
+            .iter()
+            .map(|kind| {
+                let res = result(kind);
+                if let Some(reporter) = reporter.as_mut() {
+                    reporter.start(interaction);
+                    reporter.report(interaction, &res);
+                }
+                res
+            })
+            .collect()
+    }
+
+    /// Reports every check of an interaction as skipped with the given reason.
+    pub(crate) fn skip(
+        interaction: &Interaction,
+        reason: &str,
+        reporter: Option<&mut Reporter<'_>>,
+    ) -> Vec<CheckResult> {
+        Self::conclude(interaction, reporter, |kind| {
+            CheckResult::skipped(kind, interaction, reason)
+        })
+    }
+
+    /// Results for an interaction that should not be performed at all: one
+    /// left out by the selection, or whose `when:` condition does not hold.
+    pub(crate) fn gate(
+        &self,
+        context: &Context,
+        interaction: &Interaction,
+        reporter: Option<&mut Reporter<'_>>,
+    ) -> Option<Vec<CheckResult>> {
+        if let Some(reason) = self
+            .selection
+            .excludes(interaction, &check_kinds(interaction))
+        {
+            // params are still required, so a partial run catches missing ones
+            return Some(match interaction.ensure_requirements(context) {
+                Ok(()) => Self::skip(interaction, &reason, reporter),
+                Err(err) => Self::conclude(interaction, reporter, |kind| {
+                    CheckResult::invalid_err(kind, interaction, &err.to_string())
+                }),
+            });
+        }
+        let when = interaction.when.as_ref()?;
+        match condition::evaluate(when, &context.vars_bag, &context.var_format()) {
+            Ok(true) => None,
+            Ok(false) => Some(Self::skip(
+                interaction,
+                &format!("condition not met: {when}"),
+                reporter,
+            )),
+            Err(err) => Some(Self::conclude(interaction, reporter, |kind| {
+                CheckResult::invalid_err(kind, interaction, &format!("invalid condition: {err}"))
+            })),
+        }
+    }
+
+    /// When skipping dependents, the reason an interaction with the given
+    /// `data_dependencies` should not run, given the indices that failed so
+    /// far.
+    pub(crate) fn skip_reason(
+        &self,
+        sequence: &[Interaction],
+        deps: &[usize],
+        failed: &[usize],
+    ) -> Option<String> {
+        if !self.runner.skip_dependents {
+            return None;
+        }
+        let failed_ids = deps
+            .iter()
+            .filter(|d| failed.contains(d))
+            .map(|d| sequence[*d].request.get_id())
+            .collect::<Vec<_>>();
+        if failed_ids.is_empty() {
+            None
+        } else {
+            Some(format!("depends on failed '{}'", failed_ids.join("', '")))
+        }
+    }
+
+    /// Marks a check result with the outcome expected of it: its
+    /// interaction's `expect:`, or a violation in a flipped run.
+    pub(crate) fn with_expectation(
+        &self,
+        mut res: CheckResult,
+        interaction: &Interaction,
+    ) -> CheckResult {
+        res.expect = interaction
+            .expect
+            .or_else(|| self.flip.then_some(Expect::Violation));
+        res
+    }
+
+    pub(crate) fn is_ok(&self, results: &[CheckResult]) -> bool {
+        self.passed(results.iter())
+    }
+
+    /// Whether results end as expected, ignoring skipped ones. Results of
+    /// interactions without `expect:` should pass, or fail with violations
+    /// when `flip` is set.
+    pub(crate) fn passed<'r>(&self, results: impl Iterator<Item = &'r CheckResult>) -> bool {
+        let default = if self.flip {
+            Expect::Violation
+        } else {
+            Expect::Pass
+        };
+        results
+            .filter(|r| r.skipped.is_none())
+            .all(|r| r.outcome() == r.expect.unwrap_or(default))
+    }
+}
+
+/// Names of the checks an interaction declares, in the order they run.
+fn check_kinds(interaction: &Interaction) -> Vec<String> {
+    let mut kinds = vec![];
+    if interaction.response.is_some() {
+        kinds.push(content::NAME.to_string());
+    }
+    if interaction.benchmark.is_some() {
+        kinds.push(bench::NAME.to_string());
+    }
+    if interaction.cert.is_some() {
+        kinds.push(cert::NAME.to_string());
+    }
+    if let Some(custom) = &interaction.checks {
+        kinds.extend(custom.keys().cloned());
+    }
+    if kinds.is_empty() {
+        kinds.push(content::NAME.to_string());
+    }
+    kinds
+}
+
+/// Applies a `retry:` policy to the result of one attempt. Returns whether
+/// attempts should stop, in which case the result is final.
+fn settle_attempt(retry: &Retry, kind: &str, res: &mut CheckResult, attempt: u32) -> bool {
+    // `until:` is matched whenever there is a response to match it against,
+    // otherwise the check itself has to pass
+    let until_violations = match (&retry.until, &res.response) {
+        (Some(until), Some(resp)) => Some(RegexMatcher::new(kind).is_match(resp, Some(until))),
+        _ => None,
+    };
+    let done = until_violations.as_ref().map_or_else(
+        || res.outcome() == res.expect.unwrap_or(Expect::Pass),
+        Vec::is_empty,
+    );
+    if done || attempt >= retry.attempts {
+        if !done {
+            res.violations.extend(until_violations.unwrap_or_default());
+        }
+        res.attempts = Some(attempt);
+        return true;
+    }
+    false
+}
+
+/// Sets a matrix row's vars for one iteration, returning what they replaced.
+fn shadow_vars(context: &mut Context, vars: &VarsBag) -> Vec<(String, Option<String>)> {
+    vars.iter()
+        .map(|(k, v)| (k.clone(), context.vars_bag.insert(k.clone(), v.clone())))
+        .collect()
+}
+
+fn restore_vars(context: &mut Context, shadowed: Vec<(String, Option<String>)>) {
+    for (k, previous) in shadowed {
+        match previous {
+            Some(v) => context.vars_bag.insert(k, v),
+            None => context.vars_bag.remove(&k),
+        };
+    }
 }
 
 #[allow(clippy::module_name_repetitions)]
 pub struct SequenceRunner<'a> {
     sender: &'a dyn Sender,
-    flip: bool,
-    reporters: HashMap<String, ReporterConfig>,
+    registry: CheckRegistry,
+    settings: Settings,
+    journal: Journal,
 }
 
 impl<'a> SequenceRunner<'a> {
@@ -58,42 +301,353 @@ impl<'a> SequenceRunner<'a> {
     ) -> Self {
         Self {
             sender,
-            flip,
-            reporters,
+            registry: CheckRegistry::new(),
+            settings: Settings {
+                flip,
+                reporters,
+                runner: Runner::default(),
+                selection: Selection::default(),
+                events: None,
+            },
+            journal: Journal::default(),
+        }
+    }
+
+    /// A runner sending through `sender`, for the async runner to drive.
+    pub(crate) fn from_parts(
+        sender: &'a dyn Sender,
+        registry: CheckRegistry,
+        settings: Settings,
+        checkpoint: Option<PathBuf>,
+    ) -> Self {
+        let mut journal = Journal::default();
+        journal.path = checkpoint;
+        Self {
+            sender,
+            registry,
+            settings,
+            journal,
         }
     }
 
     #[must_use]
     pub fn from_opts(run_opts: &'a RunOptions) -> Self {
         SequenceRunner {
-            flip: run_opts.flip,
             sender: run_opts.sender.as_ref(),
-            reporters: run_opts.reporters.clone(),
+            registry: run_opts.registry.clone(),
+            settings: Settings {
+                flip: run_opts.flip,
+                reporters: run_opts.reporters.clone(),
+                runner: run_opts.runner.clone(),
+                selection: run_opts.selection.clone(),
+                events: None,
+            },
+            journal: Journal::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_registry(mut self, registry: CheckRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    #[must_use]
+    pub fn with_runner(mut self, runner: Runner) -> Self {
+        self.settings.runner = runner;
+        self
+    }
+
+    #[must_use]
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.settings.selection = selection;
+        self
+    }
+
+    /// Streams `RunEvent`s of the run to `events`, next to the reporters.
+    #[must_use]
+    pub fn with_events(mut self, events: RunEvents) -> Self {
+        self.settings.events = Some(events);
+        self
+    }
+
+    /// Saves a `Checkpoint` of the context and results to `path` after every
+    /// interaction of `run`, `run_sequence` and `resume`, and of a
+    /// `ParallelRunner` built on this runner.
+    #[must_use]
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.journal.path = Some(path.into());
+        self
+    }
+
+    /// Leaves reporting to the caller, handing back the reporters configured.
+    pub(crate) fn take_reporters(&mut self) -> HashMap<String, ReporterConfig> {
+        std::mem::take(&mut self.settings.reporters)
+    }
+
+    pub(crate) const fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub(crate) const fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    /// Builds one check per kind the interaction declares. Content runs first
+    /// so that vars it captures are available to the checks that follow.
+    fn checks_for(&self, interaction: &Interaction) -> Vec<Box<dyn Check + 'a>> {
+        let mut checks: Vec<Box<dyn Check + 'a>> = vec![];
+        if interaction.response.is_some() {
+            checks.push(Box::new(ContentCheck::new(self.sender)));
+        }
+        if interaction.benchmark.is_some() {
+            checks.push(Box::new(Bench::new(self.sender)));
+        }
+        if interaction.cert.is_some() {
+            checks.push(Box::new(Cert::new()));
+        }
+        if let Some(custom) = &interaction.checks {
+            for name in custom.keys() {
+                checks.push(custom_check(&self.registry, name, self.sender));
+            }
+        }
+        if checks.is_empty() {
+            // nothing declared: let content report the interaction as invalid
+            checks.push(Box::new(ContentCheck::new(self.sender)));
+        }
+        checks
+    }
+
+    /// Performs every check of one interaction, once per iteration of its
+    /// `matrix:` and `uri_list`, reporting each result as it completes when a
+    /// reporter is given.
+    pub(crate) fn perform(
+        &self,
+        context: &mut Context,
+        interaction: &Interaction,
+        mut reporter: Option<&mut Reporter<'_>>,
+    ) -> Vec<CheckResult> {
+        let iterations = match matrix::expand(interaction) {
+            Ok(iterations) => iterations,
+            Err(err) => {
+                return Settings::conclude(interaction, reporter, |kind| {
+                    CheckResult::invalid_err(kind, interaction, &err.to_string())
+                })
+            }
+        };
+        let mut results = vec![];
+        for iteration in iterations {
+            // row vars apply to this iteration only
+            let shadowed = shadow_vars(context, &iteration.vars);
+            let interaction = &iteration.interaction;
+            for checker in self.checks_for(interaction) {
+                if let Some(reporter) = reporter.as_mut() {
+                    reporter.start(interaction);
+                }
+                let res = if self.settings.selection.allows_kind(checker.name()) {
+                    self.attempt(checker.as_ref(), context, interaction)
+                } else {
+                    CheckResult::skipped(checker.name(), interaction, "not selected: check kind")
+                };
+                if let Some(reporter) = reporter.as_mut() {
+                    reporter.report(interaction, &res);
+                }
+                results.push(res);
+            }
+            restore_vars(context, shadowed);
+        }
+        results
+    }
+
+    /// Performs a check, repeating it as the interaction's `retry:` asks until
+    /// it passes (or its response matches `until:`) or attempts run out.
+    fn attempt(
+        &self,
+        checker: &dyn Check,
+        context: &mut Context,
+        interaction: &Interaction,
+    ) -> CheckResult {
+        let Some(retry) = &interaction.retry else {
+            let res = checker.perform(context, interaction);
+            return self.settings.with_expectation(res, interaction);
+        };
+        let mut attempt = 1;
+        loop {
+            let res = checker.perform(context, interaction);
+            let mut res = self.settings.with_expectation(res, interaction);
+            if settle_attempt(retry, checker.name(), &mut res, attempt) {
+                return res;
+            }
+            sleep(retry.delay(attempt));
+            attempt += 1;
+        }
+    }
+
+    /// Runs interactions one after the other, stopping at the first failure
+    /// when `fail_fast` is set.
+    pub(crate) fn execute(
+        &self,
+        context: &mut Context,
+        sequence: &[Interaction],
+        reporter: &mut Reporter<'_>,
+        fail_fast: bool,
+    ) -> Vec<CheckResult> {
+        let deps = data_dependencies(sequence, &context.var_format());
+        let mut failed = vec![];
+        let mut results = vec![];
+        for (idx, interaction) in sequence.iter().enumerate() {
+            if let Some(reason) = self.settings.skip_reason(sequence, &deps[idx], &failed) {
+                failed.push(idx);
+                results.extend(Settings::skip(interaction, &reason, Some(reporter)));
+                continue;
+            }
+            let res = match self.settings.gate(context, interaction, Some(reporter)) {
+                Some(res) => res,
+                None => self.perform(context, interaction, Some(reporter)),
+            };
+            self.journal.record(context, interaction, &res);
+            let ok = self.settings.is_ok(&res);
+            results.extend(res);
+            if !ok {
+                failed.push(idx);
+                if fail_fast {
+                    break;
+                }
+            }
         }
+        results
     }
 
     pub fn run(&self, context: &mut Context, sequence: &[Interaction]) -> RunnerReport {
-        let mut reporter = create_reporter(&self.reporters);
-        let results = sequence
-            .iter()
-            .map(|interaction| {
-                let checker = ContentCheck::new(self.sender);
-                reporter.start(interaction);
-                let res = checker.perform(context, interaction);
-                reporter.report(interaction, &res);
-                res
-            })
-            .collect::<Vec<_>>();
+        self.journal.start(&[]);
+        let mut reporter = self.settings.start_reporter(sequence);
+        let results = self.execute(
+            context,
+            sequence,
+            &mut reporter,
+            self.settings.exit_on_failure(),
+        );
 
         reporter.end(sequence, &results);
-        let ok = if self.flip {
-            results.iter().all(|r| !r.violations.is_empty())
-                && results.iter().all(|r| r.error.is_none())
+        let ok = self.settings.is_ok(&results);
+        RunnerReport::new(ok, results)
+    }
+
+    /// Runs `setup`, then `http_interactions`, then `teardown`. Interactions
+    /// are skipped when setup fails, and teardown always runs, with the vars
+    /// captured so far.
+    pub fn run_sequence(
+        &self,
+        context: &mut Context,
+        sequence: &SequenceInteractions,
+    ) -> RunnerReport {
+        self.run_after(context, sequence, sequence, vec![])
+    }
+
+    /// Continues a run saved with `with_checkpoint` from the interaction with
+    /// the given id: the context is restored and the results recorded before
+    /// that interaction are kept.
+    pub fn resume(
+        &self,
+        context: &mut Context,
+        sequence: &SequenceInteractions,
+        checkpoint: Checkpoint,
+        from: &str,
+    ) -> AnyResult<RunnerReport> {
+        let rest = checkpoint::remaining(sequence, from)?;
+        let prior = checkpoint.results_before(from);
+        *context = checkpoint.context;
+        Ok(self.run_after(context, sequence, &rest, prior))
+    }
+
+    // runs `rest` of `sequence`, following the results of what already ran
+    fn run_after(
+        &self,
+        context: &mut Context,
+        sequence: &SequenceInteractions,
+        rest: &SequenceInteractions,
+        prior: Vec<CheckResult>,
+    ) -> RunnerReport {
+        self.journal.start(&prior);
+        let mut reporter = self.settings.start_reporter(&rest.all_interactions());
+        let mut results = prior;
+        results.extend(
+            self.run_phases(context, rest, &mut reporter, |ctx, reporter| {
+                self.execute(
+                    ctx,
+                    &rest.http_interactions,
+                    reporter,
+                    self.settings.exit_on_failure(),
+                )
+            }),
+        );
+
+        reporter.end(&sequence.all_interactions(), &results);
+        let ok = self.settings.is_ok(&results);
+        RunnerReport::new(ok, results)
+    }
+
+    /// Wraps the results of running the main interactions with those of
+    /// setup and teardown.
+    pub(crate) fn run_phases(
+        &self,
+        context: &mut Context,
+        sequence: &SequenceInteractions,
+        reporter: &mut Reporter<'_>,
+        main: impl FnOnce(&mut Context, &mut Reporter<'_>) -> Vec<CheckResult>,
+    ) -> Vec<CheckResult> {
+        let mut results = self.execute(
+            context,
+            &sequence.setup,
+            reporter,
+            self.settings.exit_on_failure(),
+        );
+        if self.settings.is_ok(&results) {
+            results.extend(main(context, reporter));
         } else {
-            results.iter().all(|r| r.violations.is_empty())
-                && results.iter().all(|r| r.error.is_none())
-        };
-        RunnerReport { ok, results }
+            for interaction in &sequence.http_interactions {
+                results.extend(Settings::skip(interaction, "setup failed", Some(reporter)));
+            }
+        }
+        results.extend(self.execute(context, &sequence.teardown, reporter, false));
+        results
+    }
+}
+
+/// The check handling a `checks:` entry: registered, built in, or else one
+/// reporting that nothing handles it.
+fn custom_check<'s>(
+    registry: &CheckRegistry,
+    name: &str,
+    sender: &'s dyn Sender,
+) -> Box<dyn Check + 's> {
+    registry
+        .create(name, sender)
+        .or_else(|| registry::builtin(name, sender))
+        .unwrap_or_else(|| Box::new(Unregistered::new(name)))
+}
+
+/// Stands in for a `checks:` entry that has no factory in the registry.
+struct Unregistered {
+    name: String,
+}
+impl Unregistered {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+impl Check for Unregistered {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn perform(&self, _context: &mut Context, interaction: &Interaction) -> CheckResult {
+        CheckResult::invalid_err(
+            &self.name,
+            interaction,
+            &format!("no check registered for '{}'", self.name),
+        )
     }
 }
 
@@ -101,30 +655,53 @@ impl<'a> SequenceRunner<'a> {
 pub struct RunnerReport {
     pub ok: bool,
     pub results: Vec<CheckResult>,
+    pub summary: Summary,
+}
+
+impl RunnerReport {
+    #[must_use]
+    pub fn new(ok: bool, results: Vec<CheckResult>) -> Self {
+        let summary = Summary::from_results(&results);
+        Self {
+            ok,
+            results,
+            summary,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data::{CheckStatus, StatusCounts};
     use mockito::{mock, server_address};
 
     const ITC_OK: &str = include_str!("fixtures/ok.yaml");
     const ITC_OK_THEN_ERROR: &str = include_str!("fixtures/ok-then-error.yaml");
+    const ITC_BENCH: &str = include_str!("fixtures/bench.yaml");
+    const ITC_LOGIN_THEN_PROFILE: &str = include_str!("fixtures/login-then-profile.yaml");
+    const ITC_SETUP_TEARDOWN: &str = include_str!("fixtures/setup-teardown.yaml");
 
     fn execute_test(seq: &str, flip: bool) -> RunnerReport {
+        execute_test_with_runner(seq, flip, Runner::default())
+    }
+
+    fn execute_test_with_runner(seq: &str, flip: bool, behavior: Runner) -> RunnerReport {
         let interactions = Interaction::sequence_interactions_from_yaml(seq).unwrap();
         let mut ctx = Context::new();
         ctx.vars_bag
             .insert("host".to_string(), server_address().to_string());
 
         let sender = SenderBuilder::build(SenderOptions { dry_run: None });
-        let runner = SequenceRunner::new(sender.as_ref(), flip, HashMap::new());
+        let runner =
+            SequenceRunner::new(sender.as_ref(), flip, HashMap::new()).with_runner(behavior);
 
         runner.run(&mut ctx, &interactions)
     }
 
     #[test]
     fn test_runner_return_status_no_violations() {
+        let _m1 = mock("GET", "/api/ok").with_status(200).create();
         let report = execute_test(ITC_OK, false);
         assert!(report.ok);
     }
@@ -144,6 +721,507 @@ mod tests {
         assert!(!report.ok);
     }
 
+    #[test]
+    fn test_runner_dispatches_every_declared_check() {
+        let _m1 = mock("GET", "/api/ok").with_status(200).create();
+        let report = execute_test(ITC_BENCH, false);
+        assert_eq!(
+            report
+                .results
+                .iter()
+                .map(|r| r.kind.as_str())
+                .collect::<Vec<_>>(),
+            vec!["content", "bench"]
+        );
+        assert!(report.ok);
+    }
+
+    #[test]
+    fn test_runner_exit_on_failure_stops_at_first_failure() {
+        let _m1 = mock("GET", "/login").with_status(401).create();
+        let _m2 = mock("GET", "/health").with_status(200).create();
+        let report = execute_test_with_runner(
+            ITC_LOGIN_THEN_PROFILE,
+            false,
+            Runner {
+                exit_on_failure: true,
+                ..Runner::default()
+            },
+        );
+        assert!(!report.ok);
+        assert_eq!(report.results.len(), 1);
+        assert_eq!(report.results[0].request.get_id(), "login");
+    }
+
+    #[test]
+    fn test_runner_skips_dependents_of_failed_interaction() {
+        let _m1 = mock("GET", "/login").with_status(401).create();
+        let _m2 = mock("GET", "/health").with_status(200).create();
+        let report = execute_test_with_runner(
+            ITC_LOGIN_THEN_PROFILE,
+            false,
+            Runner {
+                skip_dependents: true,
+                ..Runner::default()
+            },
+        );
+        assert!(!report.ok);
+        assert_eq!(report.results.len(), 3);
+        assert!(report.results[0].skipped.is_none());
+        assert!(report.results[1].skipped.is_none());
+        assert!(report.results[1].violations.is_empty());
+        assert_eq!(
+            report.results[2].skipped.as_deref(),
+            Some("depends on failed 'login'")
+        );
+    }
+
+    #[test]
+    fn test_runner_retries_until_response_matches() {
+        let _m1 = mock("GET", "/job")
+            .with_body("status: pending")
+            .expect(2)
+            .create();
+        let _m2 = mock("GET", "/job").with_body("status: done").create();
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    uri: http://{{host}}/job
+  response:
+    status_code: "200"
+  retry:
+    attempts: 5
+    backoff: exponential
+    delay_ms: 10
+    until:
+      body: done
+"#,
+        )
+        .unwrap();
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let runner = SequenceRunner::new(sender.as_ref(), false, HashMap::new());
+        let report = runner.run(&mut ctx, &interactions);
+
+        assert!(report.ok);
+        assert_eq!(report.results[0].attempts, Some(3));
+        assert_eq!(
+            report.results[0].response.as_ref().unwrap().body.as_deref(),
+            Some("status: done")
+        );
+    }
+
+    #[test]
+    fn test_runner_reports_unmet_until_after_last_attempt() {
+        let _m1 = mock("GET", "/job").with_body("status: pending").create();
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    uri: http://{{host}}/job
+  response:
+    status_code: "200"
+  retry:
+    attempts: 2
+    until:
+      body: done
+"#,
+        )
+        .unwrap();
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let runner = SequenceRunner::new(sender.as_ref(), false, HashMap::new());
+        let report = runner.run(&mut ctx, &interactions);
+
+        assert!(!report.ok);
+        assert_eq!(report.results[0].attempts, Some(2));
+        assert_eq!(report.results[0].violations[0].subject, "body");
+    }
+
+    #[test]
+    fn test_runner_skips_interactions_when_condition_fails() {
+        let _m1 = mock("GET", "/api/ok").with_status(200).create();
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: prod-only
+    uri: http://{{host}}/api/prod
+  response:
+    status_code: "200"
+  when: '{{env}} == "prod"'
+- request:
+    id: always
+    uri: http://{{host}}/api/ok
+  response:
+    status_code: "200"
+  when: "{{host}} is set"
+"#,
+        )
+        .unwrap();
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+        ctx.vars_bag.insert("env".to_string(), "dev".to_string());
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let runner = SequenceRunner::new(sender.as_ref(), false, HashMap::new());
+        let report = runner.run(&mut ctx, &interactions);
+
+        assert!(report.ok);
+        assert_eq!(
+            report.results[0].skipped.as_deref(),
+            Some(r#"condition not met: {{env}} == "prod""#)
+        );
+        assert!(report.results[1].skipped.is_none());
+        assert!(report.results[1].response.is_some());
+    }
+
+    #[test]
+    fn test_runner_resumes_from_checkpoint() {
+        let login = mock("GET", "/ckpt/login")
+            .with_body("token: t0k3n")
+            .expect(1)
+            .create();
+        let sequence = SequenceInteractions::from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: login
+    uri: http://{{host}}/ckpt/login
+    vars:
+      token:
+        from: /body
+        expr: "token: (.*)"
+  response:
+    status_code: "200"
+- request:
+    id: profile
+    uri: http://{{host}}/ckpt/me
+    headers:
+      Authorization:
+      - Bearer {{token}}
+  response:
+    status_code: "200"
+"#,
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("spk-checkpoint-{}.json", std::process::id()));
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let runner =
+            SequenceRunner::new(sender.as_ref(), false, HashMap::new()).with_checkpoint(&path);
+
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+        let report = runner.run_sequence(&mut ctx, &sequence);
+        assert!(!report.ok);
+
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        assert_eq!(checkpoint.last.as_deref(), Some("profile"));
+        assert_eq!(checkpoint.results.len(), 2);
+
+        let _profile = mock("GET", "/ckpt/me")
+            .match_header("authorization", "Bearer t0k3n")
+            .create();
+        let mut ctx = Context::new();
+        let report = runner
+            .resume(&mut ctx, &sequence, checkpoint, "profile")
+            .unwrap();
+        assert!(report.ok);
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(ctx.vars_bag.get("token").unwrap(), "t0k3n");
+        login.assert();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_runner_judges_each_interaction_by_its_expectation() {
+        let _m1 = mock("GET", "/api/ok").with_status(200).create();
+        let seq = r#"
+http_interactions:
+- request:
+    id: login
+    uri: http://{{host}}/api/ok
+  response:
+    status_code: "200"
+- request:
+    id: bypass
+    uri: http://{{host}}/api/ok
+  response:
+    status_code: "401"
+  expect: violation
+- request:
+    id: broken
+    uri: http://{{host}}/api/ok?{{missing}}
+    params:
+    - name: missing
+      desc: never given
+  response:
+    status_code: "200"
+  expect: error
+"#;
+        let report = execute_test(seq, false);
+        assert!(report.ok);
+        assert_eq!(
+            report
+                .results
+                .iter()
+                .map(|r| (r.expect, r.outcome()))
+                .collect::<Vec<_>>(),
+            vec![
+                (None, Expect::Pass),
+                (Some(Expect::Violation), Expect::Violation),
+                (Some(Expect::Error), Expect::Error),
+            ]
+        );
+
+        // negative tests that fail as expected count as passed
+        assert_eq!(report.summary.counts.passed, 3);
+        assert!(report.summary.failed_ids.is_empty());
+        let json = serde_json::to_value(&report.results[1]).unwrap();
+        assert_eq!(json["status"], "passed");
+
+        // the global flip only applies to interactions without `expect:`
+        let report = execute_test(seq, true);
+        assert!(!report.ok);
+        assert_eq!(report.summary.counts.failed, 1);
+        assert_eq!(report.results[0].expect, Some(Expect::Violation));
+    }
+
+    #[test]
+    fn test_runner_runs_selected_interactions_only() {
+        let _m1 = mock("GET", "/api/ok").with_status(200).create();
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: smoke-health
+    uri: http://{{host}}/api/ok
+  response:
+    status_code: "200"
+  tags: [smoke]
+- request:
+    id: nightly-report
+    uri: http://{{host}}/api/report
+  response:
+    status_code: "200"
+- request:
+    id: nightly-tenant
+    uri: http://{{host}}/api/{{tenant}}
+    params:
+    - name: tenant
+      desc: tenant to report on
+  response:
+    status_code: "200"
+"#,
+        )
+        .unwrap();
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let runner =
+            SequenceRunner::new(sender.as_ref(), false, HashMap::new()).with_selection(Selection {
+                include_tags: vec!["smoke".to_string()],
+                ..Selection::default()
+            });
+        let report = runner.run(&mut ctx, &interactions);
+
+        assert!(!report.ok);
+        assert!(report.results[0].skipped.is_none());
+        assert!(report.results[0].violations.is_empty());
+        assert_eq!(
+            report.results[1].skipped.as_deref(),
+            Some("not selected: not tagged 'smoke'")
+        );
+        assert!(report.results[2]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("Missing required params"));
+    }
+
+    #[test]
+    fn test_runner_expands_uri_list_and_matrix() {
+        let _m1 = mock("GET", "/users/joe").with_status(200).create();
+        let _m2 = mock("GET", "/admins/joe").with_status(200).create();
+        let _m3 = mock("GET", "/users/ann").with_status(200).create();
+        let _m4 = mock("GET", "/admins/ann").with_status(403).create();
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    id: access
+    uri: http://{{host}}/
+    uri_list:
+    - http://{{host}}/users/{{user}}
+    - http://{{host}}/admins/{{user}}
+  response:
+    status_code: "200"
+  matrix:
+    values:
+    - user: joe
+    - user: ann
+"#,
+        )
+        .unwrap();
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let runner = SequenceRunner::new(sender.as_ref(), false, HashMap::new());
+        let report = runner.run(&mut ctx, &interactions);
+
+        assert_eq!(
+            report
+                .results
+                .iter()
+                .map(|r| (r.request.get_id(), r.violations.is_empty()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("access[0][0]".to_string(), true),
+                ("access[0][1]".to_string(), true),
+                ("access[1][0]".to_string(), true),
+                ("access[1][1]".to_string(), false),
+            ]
+        );
+        assert!(!ctx.vars_bag.contains_key("user"));
+    }
+
+    fn execute_sequence_test(exit_on_failure: bool) -> RunnerReport {
+        let sequence = SequenceInteractions::from_yaml(ITC_SETUP_TEARDOWN).unwrap();
+        let mut ctx = Context::new();
+        ctx.vars_bag
+            .insert("host".to_string(), server_address().to_string());
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let runner =
+            SequenceRunner::new(sender.as_ref(), false, HashMap::new()).with_runner(Runner {
+                exit_on_failure,
+                ..Runner::default()
+            });
+        runner.run_sequence(&mut ctx, &sequence)
+    }
+
+    #[test]
+    fn test_runner_teardown_runs_after_fail_fast_abort() {
+        let _m1 = mock("POST", "/users")
+            .with_status(201)
+            .with_body("42")
+            .create();
+        let _m2 = mock("GET", "/users/42/admin").with_status(200).create();
+        let m3 = mock("DELETE", "/users/42").with_status(204).create();
+        let report = execute_sequence_test(true);
+
+        m3.assert();
+        assert!(!report.ok);
+        assert_eq!(
+            report
+                .results
+                .iter()
+                .map(|r| r.request.get_id())
+                .collect::<Vec<_>>(),
+            vec!["create user", "forbidden", "delete user"]
+        );
+        assert!(report.results[2].violations.is_empty());
+    }
+
+    #[test]
+    fn test_runner_skips_interactions_when_setup_fails() {
+        let _m1 = mock("POST", "/users").with_status(500).create();
+        let m2 = mock("DELETE", "/users/").with_status(204).create();
+        let report = execute_sequence_test(false);
+
+        m2.assert();
+        assert!(!report.ok);
+        assert_eq!(report.results[1].skipped.as_deref(), Some("setup failed"));
+        assert_eq!(report.results[2].skipped.as_deref(), Some("setup failed"));
+        assert!(report.results[3].skipped.is_none());
+    }
+
+    #[test]
+    fn test_runner_report_summarizes_results() {
+        let _m1 = mock("POST", "/users").with_status(500).create();
+        let _m2 = mock("DELETE", "/users/").with_status(204).create();
+        let report = execute_sequence_test(false);
+
+        let summary = &report.summary;
+        assert_eq!(report.results[0].status(), CheckStatus::Failed);
+        assert_eq!(
+            summary.counts,
+            StatusCounts {
+                passed: 1,
+                failed: 1,
+                errored: 0,
+                skipped: 2,
+            }
+        );
+        assert_eq!(summary.counts_by_kind["content"].total(), 4);
+        assert_eq!(summary.failed_ids, vec!["create user"]);
+        assert_eq!(summary.durations_by_kind["content"], summary.duration);
+    }
+
+    struct TenantCheck {}
+    impl Check for TenantCheck {
+        fn name(&self) -> &str {
+            "tenant"
+        }
+        fn perform(&self, _context: &mut Context, interaction: &Interaction) -> CheckResult {
+            let tenant = interaction.check_config("tenant").unwrap()["tenant"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            let res = CheckResult::new(
+                self.name(),
+                interaction.request.clone(),
+                None,
+                vec![],
+                std::time::Duration::new(0, 0),
+            );
+            if tenant == "acme" {
+                res
+            } else {
+                res.with_error(&format!("unexpected tenant {tenant}"))
+            }
+        }
+    }
+
+    #[test]
+    fn test_runner_dispatches_registered_checks() {
+        let interactions = Interaction::sequence_interactions_from_yaml(
+            r#"
+http_interactions:
+- request:
+    uri: http://localhost/tenants
+  checks:
+    tenant:
+      tenant: acme
+    unknown: {}
+"#,
+        )
+        .unwrap();
+        let sender = SenderBuilder::build(SenderOptions { dry_run: None });
+        let mut registry = CheckRegistry::new();
+        registry.register("tenant", |_| Box::new(TenantCheck {}));
+        let runner =
+            SequenceRunner::new(sender.as_ref(), false, HashMap::new()).with_registry(registry);
+        let report = runner.run(&mut Context::new(), &interactions);
+
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(report.results[0].kind, "tenant");
+        assert!(report.results[0].error.is_none());
+        assert_eq!(report.results[1].kind, "unknown");
+        assert_eq!(
+            report.results[1].error.as_deref(),
+            Some("no check registered for 'unknown'")
+        );
+        assert!(!report.ok);
+    }
+
     #[test]
     fn test_runner_flip_return_status_no_violations() {
         let _m1 = mock("GET", "/api/ok").create();
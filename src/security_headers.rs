@@ -0,0 +1,348 @@
+use anyhow::{anyhow, Result as AnyResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::data::{Cause, Check, CheckResult, Context, Interaction, Sender, Violation};
+use crate::registry::CheckConfig;
+pub const NAME: &str = "security_headers";
+
+const PRESETS: &[&str] = &["strict", "baseline"];
+
+/// What the `security_headers` check requires of a response. It starts from a
+/// preset, `strict` unless the config names another, and any field set in the
+/// interaction's config overrides the preset's:
+///
+/// ```yaml
+/// checks:
+///   security_headers:
+///     preset: baseline
+///     hsts_min_max_age: 31536000
+///     referrer_policies: [no-referrer]
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct HeaderPolicy {
+    /// Minimum `Strict-Transport-Security` max-age in seconds, `null` to not
+    /// require HSTS.
+    pub hsts_min_max_age: Option<u64>,
+    pub hsts_include_subdomains: bool,
+    /// Require a `Content-Security-Policy`.
+    pub csp: bool,
+    pub csp_allow_unsafe_inline: bool,
+    pub csp_allow_unsafe_eval: bool,
+    /// Require `X-Content-Type-Options: nosniff`.
+    pub content_type_options: bool,
+    /// Require `X-Frame-Options` of DENY or SAMEORIGIN, or a CSP with
+    /// `frame-ancestors`.
+    pub frame_options: bool,
+    /// Accepted `Referrer-Policy` values, empty to not require one.
+    pub referrer_policies: Vec<String>,
+    /// Require a `Permissions-Policy`.
+    pub permissions_policy: bool,
+}
+
+impl HeaderPolicy {
+    /// A named preset, `strict` or `baseline`.
+    pub fn preset(name: &str) -> AnyResult<Self> {
+        match name {
+            "strict" => Ok(Self {
+                hsts_min_max_age: Some(31_536_000),
+                hsts_include_subdomains: true,
+                csp: true,
+                csp_allow_unsafe_inline: false,
+                csp_allow_unsafe_eval: false,
+                content_type_options: true,
+                frame_options: true,
+                referrer_policies: vec![
+                    "no-referrer".to_string(),
+                    "same-origin".to_string(),
+                    "strict-origin".to_string(),
+                    "strict-origin-when-cross-origin".to_string(),
+                ],
+                permissions_policy: true,
+            }),
+            "baseline" => Ok(Self {
+                hsts_min_max_age: Some(15_552_000),
+                hsts_include_subdomains: false,
+                csp: false,
+                csp_allow_unsafe_inline: false,
+                csp_allow_unsafe_eval: false,
+                content_type_options: true,
+                frame_options: true,
+                referrer_policies: vec![],
+                permissions_policy: false,
+            }),
+            _ => Err(anyhow!(
+                "unknown {} preset '{}' (known: {})",
+                NAME,
+                name,
+                PRESETS.join(", ")
+            )),
+        }
+    }
+
+    /// Every way `headers` fall short of the policy.
+    #[must_use]
+    pub fn evaluate(&self, headers: &HashMap<String, Vec<String>>) -> Vec<Violation> {
+        let mut violations = vec![];
+        let csp = header(headers, "content-security-policy");
+
+        if let Some(min_max_age) = self.hsts_min_max_age {
+            violations.extend(hsts_violations(
+                header(headers, "strict-transport-security"),
+                min_max_age,
+                self.hsts_include_subdomains,
+            ));
+        }
+
+        if let Some(csp) = csp.as_deref() {
+            let sources = csp.to_lowercase();
+            if !self.csp_allow_unsafe_inline && sources.contains("'unsafe-inline'") {
+                violations.push(mismatch(
+                    "content-security-policy",
+                    csp,
+                    "no 'unsafe-inline'",
+                ));
+            }
+            if !self.csp_allow_unsafe_eval && sources.contains("'unsafe-eval'") {
+                violations.push(mismatch("content-security-policy", csp, "no 'unsafe-eval'"));
+            }
+        } else if self.csp {
+            violations.push(missing("content-security-policy", "present"));
+        }
+
+        if self.content_type_options {
+            match header(headers, "x-content-type-options") {
+                Some(value) if value.trim().eq_ignore_ascii_case("nosniff") => {}
+                Some(value) => {
+                    violations.push(mismatch("x-content-type-options", &value, "nosniff"));
+                }
+                None => violations.push(missing("x-content-type-options", "nosniff")),
+            }
+        }
+
+        if self.frame_options {
+            let ancestors = csp
+                .as_deref()
+                .is_some_and(|csp| directive(csp, "frame-ancestors").is_some());
+            let recorded = "DENY, SAMEORIGIN or CSP frame-ancestors";
+            match header(headers, "x-frame-options") {
+                _ if ancestors => {}
+                Some(value)
+                    if ["deny", "sameorigin"].contains(&value.trim().to_lowercase().as_str()) => {}
+                Some(value) => violations.push(mismatch("x-frame-options", &value, recorded)),
+                None => violations.push(missing("x-frame-options", recorded)),
+            }
+        }
+
+        if !self.referrer_policies.is_empty() {
+            let recorded = self.referrer_policies.join(", ");
+            match header(headers, "referrer-policy") {
+                // browsers apply the last policy they understand
+                Some(value)
+                    if value.rsplit(',').next().is_some_and(|last| {
+                        self.referrer_policies
+                            .iter()
+                            .any(|p| p.eq_ignore_ascii_case(last.trim()))
+                    }) => {}
+                Some(value) => violations.push(mismatch("referrer-policy", &value, &recorded)),
+                None => violations.push(missing("referrer-policy", &recorded)),
+            }
+        }
+
+        if self.permissions_policy
+            && header(headers, "permissions-policy").is_none_or(|v| v.trim().is_empty())
+        {
+            violations.push(missing("permissions-policy", "present"));
+        }
+
+        violations
+    }
+}
+
+impl CheckConfig for HeaderPolicy {
+    /// The preset named in `config`, with the rest of `config` over it.
+    fn from_config(config: Option<&serde_json::Value>) -> AnyResult<Self> {
+        let mut overrides = match config {
+            None | Some(serde_json::Value::Null) => serde_json::Map::new(),
+            Some(serde_json::Value::Object(map)) => map.clone(),
+            Some(other) => return Err(anyhow!("{} config must be a map, got {}", NAME, other)),
+        };
+        let preset = match overrides.remove("preset") {
+            Some(serde_json::Value::String(name)) => name,
+            Some(other) => return Err(anyhow!("{} preset must be a name, got {}", NAME, other)),
+            None => "strict".to_string(),
+        };
+        let mut policy = serde_json::to_value(Self::preset(&preset)?)?;
+        if let serde_json::Value::Object(fields) = &mut policy {
+            fields.extend(overrides);
+        }
+        Ok(serde_json::from_value(policy)?)
+    }
+}
+
+// all values of a header, whatever its case, joined as one
+pub(crate) fn header(headers: &HashMap<String, Vec<String>>, name: &str) -> Option<String> {
+    let values = headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+        .flat_map(|(_, values)| values.iter().map(String::as_str))
+        .collect::<Vec<_>>();
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+// value of a `;` separated directive, empty for a flag such as includeSubDomains
+fn directive<'v>(value: &'v str, name: &str) -> Option<&'v str> {
+    value.split(';').find_map(|part| {
+        let part = part.trim();
+        let (key, rest) = part
+            .split_once(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or((part, ""));
+        key.eq_ignore_ascii_case(name).then(|| rest.trim())
+    })
+}
+
+/// Violations of a `Strict-Transport-Security` value against a minimum
+/// max-age, and includeSubDomains when required.
+#[must_use]
+pub fn hsts_violations(
+    value: Option<String>,
+    min_max_age: u64,
+    include_subdomains: bool,
+) -> Vec<Violation> {
+    let name = "strict-transport-security";
+    let Some(value) = value else {
+        return vec![missing(name, &format!("max-age >= {min_max_age}"))];
+    };
+    let mut violations = vec![];
+    let max_age = directive(&value, "max-age")
+        .and_then(|age| age.trim_matches('"').parse::<u64>().ok())
+        .unwrap_or_default();
+    if max_age < min_max_age {
+        violations.push(mismatch(name, &value, &format!("max-age >= {min_max_age}")));
+    }
+    if include_subdomains && directive(&value, "includesubdomains").is_none() {
+        violations.push(mismatch(name, &value, "includeSubDomains"));
+    }
+    violations
+}
+
+fn missing(name: &str, recorded: &str) -> Violation {
+    Violation {
+        kind: NAME.to_string(),
+        cause: Cause::WireMissing,
+        subject: "headers".to_string(),
+        on: Some(name.to_string()),
+        wire: None,
+        recorded: recorded.to_string(),
+    }
+}
+
+fn mismatch(name: &str, wire: &str, recorded: &str) -> Violation {
+    Violation {
+        kind: NAME.to_string(),
+        cause: Cause::Mismatch,
+        subject: "headers".to_string(),
+        on: Some(name.to_string()),
+        wire: Some(wire.to_string()),
+        recorded: recorded.to_string(),
+    }
+}
+
+/// Sends the interaction's request and holds the response headers against a
+/// `HeaderPolicy`.
+pub struct SecurityHeaders<'a> {
+    sender: &'a dyn Sender,
+}
+
+impl<'a> SecurityHeaders<'a> {
+    pub fn new(sender: &'a dyn Sender) -> Self {
+        Self { sender }
+    }
+}
+
+impl<'a> Check for SecurityHeaders<'a> {
+    fn name(&self) -> &str {
+        NAME
+    }
+    fn perform(&self, context: &mut Context, interaction: &Interaction) -> CheckResult {
+        let now = Instant::now();
+        let found = HeaderPolicy::from_config(interaction.check_config(NAME)).and_then(|policy| {
+            let resp = interaction.send_with_context(self.sender, context)?;
+            let violations = policy.evaluate(resp.headers.as_ref().unwrap_or(&HashMap::new()));
+            Ok((Some(resp), violations))
+        });
+        CheckResult::concluded(NAME, interaction, found, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::SequenceRunner;
+    use crate::sender::ReqwestSender;
+    use mockito::{mock, server_url};
+
+    #[test]
+    fn test_security_headers_preset_with_overrides() {
+        let _m = mock("GET", "/secure-headers")
+            .with_header("strict-transport-security", "max-age=86400")
+            .with_header(
+                "content-security-policy",
+                "default-src 'self'; script-src 'self' 'unsafe-inline'; frame-ancestors 'none'",
+            )
+            .with_header("x-content-type-options", "nosniff")
+            .with_header("referrer-policy", "unsafe-url")
+            .create();
+        let inter = Interaction::from_yaml(&format!(
+            r#"
+request:
+  id: headers
+  uri: {}/secure-headers
+checks:
+  security_headers:
+    preset: strict
+    hsts_min_max_age: 3600
+    permissions_policy: false
+"#,
+            server_url()
+        ))
+        .unwrap();
+        let sender = ReqwestSender::new();
+        let runner = SequenceRunner::new(&sender, false, HashMap::new());
+        let report = runner.run(&mut Context::new(), &[inter]);
+
+        let found = report.results[0]
+            .violations
+            .iter()
+            .map(|v| (v.on.clone().unwrap(), v.recorded.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "strict-transport-security".to_string(),
+                    "includeSubDomains".to_string()
+                ),
+                (
+                    "content-security-policy".to_string(),
+                    "no 'unsafe-inline'".to_string()
+                ),
+                (
+                    "referrer-policy".to_string(),
+                    "no-referrer, same-origin, strict-origin, strict-origin-when-cross-origin"
+                        .to_string()
+                ),
+            ]
+        );
+
+        let err = HeaderPolicy::from_config(Some(&serde_json::json!({ "preset": "lax" })))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "unknown security_headers preset 'lax' (known: strict, baseline)"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::data::Interaction;
+
+/// Matches `text` against a glob where `*` stands for any run of characters
+/// and `?` for exactly one.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` seen, and where in the text it started matching
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Which interactions and checks of a run to perform. Everything is selected
+/// by default; interactions left out are reported as skipped.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Only interactions with at least one of these tags.
+    pub include_tags: Vec<String>,
+    /// No interaction with any of these tags.
+    pub exclude_tags: Vec<String>,
+    /// Only interactions whose id matches one of these globs, e.g. `login*`.
+    pub ids: Vec<String>,
+    /// Only these kinds of checks, e.g. `content` or `cert`.
+    pub kinds: Vec<String>,
+}
+
+impl Selection {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.include_tags.is_empty()
+            && self.exclude_tags.is_empty()
+            && self.ids.is_empty()
+            && self.kinds.is_empty()
+    }
+
+    #[must_use]
+    pub fn allows_kind(&self, kind: &str) -> bool {
+        self.kinds.is_empty() || self.kinds.iter().any(|k| k == kind)
+    }
+
+    /// Why an interaction with the given check kinds is left out, if it is.
+    #[must_use]
+    pub fn excludes(&self, interaction: &Interaction, kinds: &[String]) -> Option<String> {
+        let tags = interaction.tags();
+        let id = interaction.request.get_id();
+        if let Some(tag) = self
+            .exclude_tags
+            .iter()
+            .find(|t| tags.contains(&t.as_str()))
+        {
+            return Some(format!("not selected: tagged '{tag}'"));
+        }
+        if !self.include_tags.is_empty()
+            && !self.include_tags.iter().any(|t| tags.contains(&t.as_str()))
+        {
+            return Some(format!(
+                "not selected: not tagged '{}'",
+                self.include_tags.join("', '")
+            ));
+        }
+        if !self.ids.is_empty() && !self.ids.iter().any(|glob| glob_match(glob, &id)) {
+            return Some(format!("not selected: id '{id}'"));
+        }
+        if !kinds.iter().any(|kind| self.allows_kind(kind)) {
+            return Some(format!("not selected: checks '{}'", kinds.join("', '")));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_excludes() {
+        assert!(glob_match("login*", "login-admin"));
+        assert!(glob_match("*-?dmin", "login-admin"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("login", "login-admin"));
+        assert!(!glob_match("*admin?", "login-admin"));
+
+        let interaction = Interaction::from_yaml(
+            r#"
+request:
+  id: login
+  uri: http://{{host}}/login
+  tags: [auth]
+tags: [smoke]
+"#,
+        )
+        .unwrap();
+        let kinds = vec!["content".to_string()];
+        let selection = |f: fn(&mut Selection)| {
+            let mut s = Selection::default();
+            f(&mut s);
+            s.excludes(&interaction, &kinds)
+        };
+
+        assert_eq!(selection(|_| {}), None);
+        assert_eq!(selection(|s| s.include_tags = vec!["auth".into()]), None);
+        assert_eq!(
+            selection(|s| s.include_tags = vec!["nightly".into()]),
+            Some("not selected: not tagged 'nightly'".to_string())
+        );
+        assert_eq!(
+            selection(|s| s.exclude_tags = vec!["smoke".into()]),
+            Some("not selected: tagged 'smoke'".to_string())
+        );
+        assert_eq!(selection(|s| s.ids = vec!["log*".into()]), None);
+        assert_eq!(
+            selection(|s| s.ids = vec!["profile".into()]),
+            Some("not selected: id 'login'".to_string())
+        );
+        assert_eq!(
+            selection(|s| s.kinds = vec!["cert".into()]),
+            Some("not selected: checks 'content'".to_string())
+        );
+    }
+}
@@ -1,11 +1,13 @@
-use crate::data::{Interaction, Response, Sender};
-use anyhow::Result as AnyResult;
+use crate::data::{AsyncSender, Interaction, Permit, Request, Response, Sender};
+use anyhow::{anyhow, Result as AnyResult};
+use async_trait::async_trait;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use rusoto_core::{credential::AwsCredentials, signature::SignedRequest, Region};
 
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::time::Duration;
+use tokio::runtime::Runtime;
 
 #[allow(clippy::module_name_repetitions)]
 pub struct SenderOptions {
@@ -36,8 +38,101 @@ impl ReqwestSender {
         Self::default()
     }
 }
+fn redirect_policy(request: &Request) -> reqwest::redirect::Policy {
+    if request.follow_redirects == Some(false) {
+        reqwest::redirect::Policy::none()
+    } else {
+        reqwest::redirect::Policy::default()
+    }
+}
+fn method_of(request: &Request) -> String {
+    request
+        .method
+        .as_ref()
+        .unwrap_or(&"GET".to_string())
+        .to_uppercase()
+}
+
+// headers sent with every request: user agent, AWS signature and the request's own
+fn request_headers(request: &Request, method: &str) -> AnyResult<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert("User-Agent", HeaderValue::from_static("keyscope/1"));
+
+    if let Some(aws) = &request.aws_auth {
+        let credentials =
+            AwsCredentials::new(aws.key.clone(), aws.secret.clone(), aws.token.clone(), None);
+        let default_region = "us-east-1".to_string();
+        let reg_str = aws.region.as_ref().unwrap_or(&default_region);
+
+        let region: Region = Region::from_str(reg_str).unwrap_or_else(|_| Region::Custom {
+            name: reg_str.to_string(),
+            endpoint: aws.endpoint.clone().unwrap_or_default(),
+        });
+
+        // note the path is '/' because at this point we only care about checking service-level access
+        let mut signed_request = SignedRequest::new(method, aws.service.as_str(), &region, "/");
+
+        signed_request.set_payload(request.body.as_ref().map(|b| b.clone().into_bytes()));
+
+        if let Some(content_type) = request
+            .headers
+            .as_ref()
+            .and_then(|h| h.get("content-type").and_then(|c| c.iter().next()))
+        {
+            signed_request.set_content_type(content_type.to_string());
+        }
+
+        signed_request.sign(&credentials);
+
+        let rh = signed_request.headers();
+
+        for h in &[
+            "x-amz-content-sha256",
+            "x-amz-date",
+            "authorization",
+            "content-type",
+            "host",
+        ] {
+            headers.insert(
+                (*h).to_string().parse::<HeaderName>().unwrap(),
+                String::from_utf8_lossy(&rh.get(*h).unwrap()[0])
+                    .parse()
+                    .unwrap(),
+            );
+        }
+
+        if let Some(token) = aws.token.as_ref() {
+            headers.insert("X-Amz-Security-Token", token.parse()?);
+        }
+    }
+
+    if let Some(request_headers) = &request.headers {
+        for (key, val) in request_headers {
+            for v in val {
+                headers.insert(
+                    key.to_lowercase().parse::<HeaderName>().unwrap(),
+                    HeaderValue::from_str(v.clone().as_str()).unwrap(),
+                );
+            }
+        }
+    };
+    Ok(headers)
+}
+
+fn response_headers(rq_headers: &HeaderMap) -> HashMap<String, Vec<String>> {
+    let mut headers: HashMap<String, Vec<String>> = HashMap::new();
+    rq_headers.iter().for_each(|(key, value)| {
+        if let Ok(value) = value.to_str() {
+            headers
+                .entry(key.to_string())
+                .or_default()
+                .push(value.to_string());
+        }
+    });
+    headers
+}
+
 impl Sender for ReqwestSender {
-    #[allow(clippy::too_many_lines)]
     fn send(&self, inter: &Interaction) -> AnyResult<Response> {
         let request = &inter.request;
         // as_request -> RQRequest
@@ -45,91 +140,22 @@ impl Sender for ReqwestSender {
         log::debug!("uri with vars: {}", uri);
         let client = reqwest::blocking::Client::builder()
             .timeout(Duration::from_millis(request.timeout_ms.unwrap_or(10000)))
+            .redirect(redirect_policy(request))
             .build()
             .unwrap();
-        let method = request
-            .method
-            .as_ref()
-            .unwrap_or(&"GET".to_string())
-            .to_uppercase();
+        let method = method_of(request);
 
         let mut rq_builder = client.request(
             reqwest::Method::from_bytes(&method.clone().into_bytes()).unwrap(),
             &uri,
         );
-        rq_builder = rq_builder.header("User-Agent", "keyscope/1");
         if let Some(basic) = &request.basic_auth {
             rq_builder = rq_builder.basic_auth(basic.user.clone(), basic.password.clone());
         }
         if let Some(form) = &request.form {
             rq_builder = rq_builder.form(form);
         }
-
-        if let Some(aws) = &request.aws_auth {
-            let credentials =
-                AwsCredentials::new(aws.key.clone(), aws.secret.clone(), aws.token.clone(), None);
-            let default_region = "us-east-1".to_string();
-            let reg_str = aws.region.as_ref().unwrap_or(&default_region);
-
-            let region: Region = Region::from_str(reg_str).unwrap_or_else(|_| Region::Custom {
-                name: reg_str.to_string(),
-                endpoint: aws.endpoint.clone().unwrap_or_default(),
-            });
-
-            let mut headers = HeaderMap::new();
-
-            // note the path is '/' because at this point we only care about checking service-level access
-            let mut signed_request =
-                SignedRequest::new(method.as_str(), aws.service.as_str(), &region, "/");
-
-            signed_request.set_payload(request.body.as_ref().map(|b| b.clone().into_bytes()));
-
-            if let Some(content_type) = request
-                .headers
-                .as_ref()
-                .and_then(|h| h.get("content-type").and_then(|c| c.iter().next()))
-            {
-                signed_request.set_content_type(content_type.to_string());
-            }
-
-            signed_request.sign(&credentials);
-
-            let rh = signed_request.headers();
-
-            for h in &[
-                "x-amz-content-sha256",
-                "x-amz-date",
-                "authorization",
-                "content-type",
-                "host",
-            ] {
-                headers.insert(
-                    (*h).to_string().parse::<HeaderName>().unwrap(),
-                    String::from_utf8_lossy(&rh.get(*h).unwrap()[0])
-                        .parse()
-                        .unwrap(),
-                );
-            }
-
-            if let Some(token) = aws.token.as_ref() {
-                headers.insert("X-Amz-Security-Token", token.parse()?);
-            }
-
-            rq_builder = rq_builder.headers(headers);
-        }
-
-        if let Some(headers) = &request.headers {
-            let mut headersmap = HeaderMap::new();
-            for (key, val) in headers {
-                for v in val {
-                    headersmap.insert(
-                        key.to_lowercase().parse::<HeaderName>().unwrap(),
-                        HeaderValue::from_str(v.clone().as_str()).unwrap(),
-                    );
-                }
-            }
-            rq_builder = rq_builder.headers(headersmap);
-        };
+        rq_builder = rq_builder.headers(request_headers(request, &method)?);
 
         if let Some(body) = &request.body {
             rq_builder = rq_builder.body(reqwest::blocking::Body::from(body.to_string()));
@@ -139,22 +165,9 @@ impl Sender for ReqwestSender {
         let rq_resp = rq_builder.send()?;
 
         // from_reqest -> RQResponse
-        let mut headers: HashMap<String, Vec<String>> = HashMap::new();
-        rq_resp.headers().iter().for_each(|(key, value)| {
-            if value.to_str().is_ok() {
-                let k = key.to_string();
-                if !headers.contains_key(&k) {
-                    headers.insert(k.to_string(), vec![]);
-                }
-                headers
-                    .get_mut(&k)
-                    .unwrap()
-                    .push(value.to_str().unwrap().to_string());
-            }
-        });
         let resp = Response {
             status_code: Some(rq_resp.status().to_string()),
-            headers: Some(headers),
+            headers: Some(response_headers(rq_resp.headers())),
             request_id: Some(request.get_id()),
             vars: None,
             body: Some(rq_resp.text().unwrap()),
@@ -164,6 +177,83 @@ impl Sender for ReqwestSender {
     }
 }
 
+/// The non-blocking counterpart of `ReqwestSender`, for use from async code.
+#[derive(Default)]
+#[allow(clippy::module_name_repetitions)]
+pub struct AsyncReqwestSender {}
+
+impl AsyncReqwestSender {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AsyncSender for AsyncReqwestSender {
+    async fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+        let request = &inter.request;
+        let uri = request.uri.clone();
+        log::debug!("uri with vars: {}", uri);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(request.timeout_ms.unwrap_or(10000)))
+            .redirect(redirect_policy(request))
+            .build()?;
+        let method = method_of(request);
+
+        let mut rq_builder = client.request(reqwest::Method::from_bytes(method.as_bytes())?, &uri);
+        if let Some(basic) = &request.basic_auth {
+            rq_builder = rq_builder.basic_auth(basic.user.clone(), basic.password.clone());
+        }
+        if let Some(form) = &request.form {
+            rq_builder = rq_builder.form(form);
+        }
+        rq_builder = rq_builder.headers(request_headers(request, &method)?);
+
+        if let Some(body) = &request.body {
+            rq_builder = rq_builder.body(body.to_string());
+        }
+
+        let rq_resp = rq_builder.send().await?;
+        Ok(Response {
+            status_code: Some(rq_resp.status().to_string()),
+            headers: Some(response_headers(rq_resp.headers())),
+            request_id: Some(request.get_id()),
+            vars: None,
+            body: Some(rq_resp.text().await?),
+        })
+    }
+}
+
+/// Exposes an `AsyncSender` through the blocking `Sender` API, driving it on
+/// a runtime of its own. It must not be used from within an async context.
+#[allow(clippy::module_name_repetitions)]
+pub struct BlockingSender<S: AsyncSender> {
+    inner: S,
+    runtime: Runtime,
+}
+
+impl<S: AsyncSender> BlockingSender<S> {
+    pub fn new(inner: S) -> AnyResult<Self> {
+        Ok(Self {
+            inner,
+            runtime: tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?,
+        })
+    }
+}
+
+impl<S: AsyncSender> Sender for BlockingSender<S> {
+    fn send(&self, inter: &Interaction) -> AnyResult<Response> {
+        self.runtime.block_on(self.inner.send(inter))
+    }
+
+    fn permit(&self, uri: &str) -> AnyResult<Permit<'_>> {
+        self.runtime.block_on(self.inner.permit(uri))
+    }
+}
+
 #[allow(clippy::module_name_repetitions)]
 pub struct DrySender {
     example: String,
@@ -197,4 +287,9 @@ impl Sender for DrySender {
             vars: None,
         })
     }
+
+    // a dry run answers from examples, so nothing may connect around it
+    fn permit(&self, uri: &str) -> AnyResult<Permit<'_>> {
+        Err(anyhow!("cannot connect to '{}' in a dry run", uri))
+    }
 }
@@ -0,0 +1,352 @@
+use anyhow::{anyhow, Result as AnyResult};
+use openssl::ssl::{SslConnector, SslMethod, SslStream, SslVerifyMode, SslVersion};
+use serde::{Deserialize, Serialize};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use crate::data::{Cause, Check, CheckResult, Context, Interaction, Sender, Violation};
+use crate::registry::CheckConfig;
+pub const NAME: &str = "tls";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    #[serde(rename = "1.0")]
+    Tls10,
+    #[serde(rename = "1.1")]
+    Tls11,
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    const fn ssl(self) -> SslVersion {
+        match self {
+            Self::Tls10 => SslVersion::TLS1,
+            Self::Tls11 => SslVersion::TLS1_1,
+            Self::Tls12 => SslVersion::TLS1_2,
+            Self::Tls13 => SslVersion::TLS1_3,
+        }
+    }
+
+    fn from_ssl(version: SslVersion) -> Option<Self> {
+        [Self::Tls10, Self::Tls11, Self::Tls12, Self::Tls13]
+            .iter()
+            .copied()
+            .find(|v| v.ssl() == version)
+    }
+
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Tls10 => "TLS 1.0",
+            Self::Tls11 => "TLS 1.1",
+            Self::Tls12 => "TLS 1.2",
+            Self::Tls13 => "TLS 1.3",
+        }
+    }
+}
+
+fn min_version() -> Option<TlsVersion> {
+    Some(TlsVersion::Tls12)
+}
+
+fn reject_versions() -> Vec<TlsVersion> {
+    vec![TlsVersion::Tls10, TlsVersion::Tls11]
+}
+
+fn reject_ciphers() -> Vec<String> {
+    ["NULL", "aNULL", "EXPORT", "RC4", "DES", "3DES", "MD5"]
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// What the `tls` check requires of a server's handshakes:
+///
+/// ```yaml
+/// checks:
+///   tls:
+///     min_version: "1.2"
+///     reject_versions: ["1.0", "1.1"]
+///     reject_ciphers: [RC4, 3DES]
+/// ```
+///
+/// Ciphers are OpenSSL cipher strings, probed over TLS 1.2 and below. Those
+/// the local OpenSSL cannot offer at all are not probed.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TlsPolicy {
+    /// Lowest version a default handshake may negotiate.
+    #[serde(default = "min_version")]
+    pub min_version: Option<TlsVersion>,
+    #[serde(default = "reject_versions")]
+    pub reject_versions: Vec<TlsVersion>,
+    #[serde(default = "reject_ciphers")]
+    pub reject_ciphers: Vec<String>,
+}
+
+impl CheckConfig for TlsPolicy {}
+
+/// One handshake configuration to try against the server.
+struct Probe<'p> {
+    min: Option<TlsVersion>,
+    max: Option<TlsVersion>,
+    ciphers: Option<&'p str>,
+}
+
+fn violation(subject: &str, wire: String, recorded: &str) -> Violation {
+    Violation {
+        kind: NAME.to_string(),
+        cause: Cause::Mismatch,
+        subject: subject.to_string(),
+        on: None,
+        wire: Some(wire),
+        recorded: recorded.to_string(),
+    }
+}
+
+/// Tries handshakes with specific protocol versions and cipher suites,
+/// reporting every weak configuration the server accepts. Handshakes are
+/// made within the sender's limits, as its own requests are.
+pub struct Tls<'a> {
+    sender: &'a dyn Sender,
+}
+
+impl<'a> Tls<'a> {
+    pub fn new(sender: &'a dyn Sender) -> Self {
+        Self { sender }
+    }
+
+    fn handshake(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+        probe: &Probe<'_>,
+    ) -> AnyResult<Option<SslStream<TcpStream>>> {
+        let _permit = self.sender.permit(&format!("https://{host}:{port}/"))?;
+        Self::connect(host, port, timeout, probe)
+    }
+
+    /// Handshakes with `host:port`, `None` when the server or the local
+    /// OpenSSL refuses the configuration.
+    fn connect(
+        host: &str,
+        port: u16,
+        timeout: Duration,
+        probe: &Probe<'_>,
+    ) -> AnyResult<Option<SslStream<TcpStream>>> {
+        let mut builder = SslConnector::builder(SslMethod::tls())?;
+        // only what the server accepts is of interest, not its certificate
+        builder.set_verify(SslVerifyMode::NONE);
+        builder.set_min_proto_version(probe.min.map(TlsVersion::ssl))?;
+        builder.set_max_proto_version(probe.max.map(TlsVersion::ssl))?;
+        // security level 0 lets the client offer the legacy setups it probes
+        let ciphers = format!("{}:@SECLEVEL=0", probe.ciphers.unwrap_or("ALL"));
+        if builder.set_cipher_list(&ciphers).is_err() {
+            return Ok(None);
+        }
+        let connector = builder.build();
+
+        let addr = (host, port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("cannot resolve '{}'", host))?;
+        let stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(connector
+            .configure()?
+            .verify_hostname(false)
+            .connect(host, stream)
+            .ok())
+    }
+
+    fn probe(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+        policy: &TlsPolicy,
+    ) -> AnyResult<Vec<Violation>> {
+        let mut violations = vec![];
+        for version in &policy.reject_versions {
+            let probe = Probe {
+                min: Some(*version),
+                max: Some(*version),
+                ciphers: None,
+            };
+            if self.handshake(host, port, timeout, &probe)?.is_some() {
+                violations.push(violation(
+                    "version",
+                    version.label().to_string(),
+                    "rejected",
+                ));
+            }
+        }
+        for ciphers in &policy.reject_ciphers {
+            let probe = Probe {
+                min: None,
+                max: Some(TlsVersion::Tls12),
+                ciphers: Some(ciphers),
+            };
+            if let Some(stream) = self.handshake(host, port, timeout, &probe)? {
+                let negotiated = stream
+                    .ssl()
+                    .current_cipher()
+                    .map_or_else(|| ciphers.clone(), |c| c.name().to_string());
+                violations.push(violation(
+                    "cipher",
+                    negotiated,
+                    &format!("{ciphers} rejected"),
+                ));
+            }
+        }
+        if let Some(min) = policy.min_version {
+            let probe = Probe {
+                min: None,
+                max: None,
+                ciphers: None,
+            };
+            let stream = self
+                .handshake(host, port, timeout, &probe)?
+                .ok_or_else(|| anyhow!("no TLS handshake with {}:{}", host, port))?;
+            let negotiated = stream.ssl().version2().and_then(TlsVersion::from_ssl);
+            if negotiated.is_none_or(|v| v < min) {
+                violations.push(violation(
+                    "negotiated version",
+                    stream.ssl().version_str().to_string(),
+                    &format!(">= {}", min.label()),
+                ));
+            }
+        }
+        Ok(violations)
+    }
+}
+
+impl<'a> Check for Tls<'a> {
+    fn name(&self) -> &str {
+        NAME
+    }
+    fn perform(&self, context: &mut Context, interaction: &Interaction) -> CheckResult {
+        let now = Instant::now();
+        let found = TlsPolicy::from_config(interaction.check_config(NAME)).and_then(|policy| {
+            let prepared = interaction.prepare_with(context)?;
+            let url = reqwest::Url::parse(&prepared.request.uri)?;
+            let host = url
+                .host_str()
+                .ok_or_else(|| anyhow!("no host in '{}'", prepared.request.uri))?;
+            let port = url.port_or_known_default().unwrap_or(443);
+            let timeout = Duration::from_millis(prepared.request.timeout_ms.unwrap_or(10000));
+            Ok((None, self.probe(host, port, timeout, &policy)?))
+        });
+        CheckResult::concluded(NAME, interaction, found, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sender::ReqwestSender;
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::ssl::{SslAcceptor, SslOptions};
+    use openssl::x509::{X509NameBuilder, X509};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // a local server speaking up to TLS 1.2 with a self-signed certificate,
+    // from TLS 1.0 and with any cipher when `legacy`
+    fn listener(legacy: bool) -> u16 {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "localhost").unwrap();
+        let name = name.build();
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(&name).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        cert.sign(&key, MessageDigest::sha256()).unwrap();
+        let cert = cert.build();
+
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor.set_private_key(&key).unwrap();
+        acceptor.set_certificate(&cert).unwrap();
+        acceptor
+            .set_max_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        if legacy {
+            acceptor.clear_options(SslOptions::NO_TLSV1 | SslOptions::NO_TLSV1_1);
+            acceptor
+                .set_min_proto_version(Some(SslVersion::TLS1))
+                .unwrap();
+            acceptor.set_cipher_list("ALL:@SECLEVEL=0").unwrap();
+        }
+        let acceptor = acceptor.build();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = acceptor.accept(stream);
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_tls_probes_versions_and_negotiated_minimum() {
+        let interaction = |port: u16| {
+            Interaction::from_yaml(&format!(
+                r#"
+request:
+  uri: https://localhost:{port}/
+checks:
+  tls:
+    min_version: "1.3"
+    reject_ciphers: [aNULL]
+"#
+            ))
+            .unwrap()
+        };
+
+        let sender = ReqwestSender::new();
+        let check = Tls::new(&sender);
+        let res = check.perform(&mut Context::new(), &interaction(listener(false)));
+
+        assert_eq!(res.error, None);
+        let found = res
+            .violations
+            .iter()
+            .map(|v| {
+                format!(
+                    "{}: {} ({})",
+                    v.subject,
+                    v.wire.clone().unwrap(),
+                    v.recorded
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec!["negotiated version: TLSv1.2 (>= TLS 1.3)".to_string()]
+        );
+
+        let res = check.perform(&mut Context::new(), &interaction(listener(true)));
+        let found = res
+            .violations
+            .iter()
+            .map(|v| format!("{}: {}", v.subject, v.wire.clone().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(found[..2], ["version: TLS 1.0", "version: TLS 1.1"]);
+        assert!(found[2].starts_with("cipher: ADH-"));
+    }
+}